@@ -0,0 +1,186 @@
+use crate::venv::PyVenvCfg;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The environment variables set by activating a venv or a conda environment.
+///
+/// It is the environment the `activate` script of a venv, or `conda activate`, would produce,
+/// so scripts shelling out to `pip`, `pytest` or console scripts find the ones of the
+/// environment instead of the system ones.
+///
+/// # Examples
+///
+/// ```
+/// use py_executer_lib::activation::Activation;
+/// use std::path::Path;
+///
+/// let activation = Activation::new(Path::new("/project/.venv"));
+/// assert_eq!(
+///     activation.get("VIRTUAL_ENV"),
+///     Some(std::ffi::OsStr::new("/project/.venv"))
+/// );
+/// assert!(activation.unset.contains(&"PYTHONHOME".to_string()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Activation {
+    /// The variables to set, with their values.
+    pub set: Vec<(String, OsString)>,
+    /// The variables to remove.
+    pub unset: Vec<String>,
+}
+
+impl Activation {
+    /// Builds the activation of the environment at `env_path`.
+    ///
+    /// A conda environment, recognized by its `conda-meta` directory, is activated like
+    /// `conda activate` does, see [`Activation::conda`], any other environment like a venv,
+    /// see [`Activation::venv`].
+    pub fn new(env_path: &Path) -> Self {
+        if is_conda_env(env_path) {
+            Activation::conda(env_path)
+        } else {
+            Activation::venv(env_path)
+        }
+    }
+
+    /// Builds the activation of the venv at `venv_path`.
+    ///
+    /// `VIRTUAL_ENV` and `VIRTUAL_ENV_PROMPT` are set, the scripts directory of the venv is
+    /// prepended to `PATH` and `PYTHONHOME` is removed.
+    pub fn venv(venv_path: &Path) -> Self {
+        let prompt = PyVenvCfg::read(venv_path)
+            .and_then(|cfg| cfg.prompt().map(str::to_string))
+            .or_else(|| {
+                venv_path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_default();
+        let scripts_dir = if cfg!(target_os = "windows") {
+            "Scripts"
+        } else {
+            "bin"
+        };
+        Activation {
+            set: vec![
+                ("VIRTUAL_ENV".to_string(), venv_path.into()),
+                ("VIRTUAL_ENV_PROMPT".to_string(), prompt.into()),
+                (
+                    "PATH".to_string(),
+                    prepend_to_path(&[venv_path.join(scripts_dir)]),
+                ),
+            ],
+            unset: vec!["PYTHONHOME".to_string()],
+        }
+    }
+
+    /// Builds the activation of the conda environment at `prefix`.
+    ///
+    /// `CONDA_PREFIX`, `CONDA_DEFAULT_ENV` and `CONDA_PROMPT_MODIFIER` are set, the binary
+    /// directories of the environment are prepended to `PATH`, and `PYTHONHOME` and
+    /// `VIRTUAL_ENV` are removed. `CONDA_SHLVL` is increased, and an already activated
+    /// environment is kept in `CONDA_PREFIX_<level>`, like `conda activate --stack` does.
+    ///
+    /// The variables of the environment, the `variables` of its `environment.yml` or set with
+    /// `conda env config vars set`, are set last, see [`conda_env_vars`].
+    pub fn conda(prefix: &Path) -> Self {
+        let bin_dirs = if cfg!(target_os = "windows") {
+            vec![
+                prefix.to_path_buf(),
+                prefix.join("Library").join("mingw-w64").join("bin"),
+                prefix.join("Library").join("usr").join("bin"),
+                prefix.join("Library").join("bin"),
+                prefix.join("Scripts"),
+                prefix.join("bin"),
+            ]
+        } else {
+            vec![prefix.join("bin")]
+        };
+        let name = prefix
+            .file_name()
+            .map_or_else(|| prefix.as_os_str().to_owned(), OsStr::to_owned);
+        let mut prompt_modifier = OsString::from("(");
+        prompt_modifier.push(&name);
+        prompt_modifier.push(") ");
+        let level: u32 = env::var("CONDA_SHLVL")
+            .ok()
+            .and_then(|level| level.trim().parse().ok())
+            .unwrap_or(0);
+        let mut set = vec![
+            ("CONDA_PREFIX".to_string(), prefix.into()),
+            ("CONDA_DEFAULT_ENV".to_string(), name),
+            ("CONDA_PROMPT_MODIFIER".to_string(), prompt_modifier),
+            ("CONDA_SHLVL".to_string(), (level + 1).to_string().into()),
+            ("PATH".to_string(), prepend_to_path(&bin_dirs)),
+        ];
+        if let Some(previous) = env::var_os("CONDA_PREFIX").filter(|_| level > 0) {
+            set.push((format!("CONDA_PREFIX_{}", level), previous));
+        }
+        set.extend(conda_env_vars(prefix));
+        Activation {
+            set,
+            unset: vec!["PYTHONHOME".to_string(), "VIRTUAL_ENV".to_string()],
+        }
+    }
+
+    /// Returns the value the activation sets for `key`.
+    pub fn get(&self, key: &str) -> Option<&OsStr> {
+        self.set
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_os_str())
+    }
+
+    /// Applies the activation to the environment of `cmd`.
+    pub fn apply(&self, cmd: &mut Command) {
+        for key in &self.unset {
+            cmd.env_remove(key);
+        }
+        cmd.envs(self.set.iter().map(|(key, value)| (key, value)));
+    }
+}
+
+/// Returns `true` if `env_path` is a conda environment.
+pub fn is_conda_env(env_path: &Path) -> bool {
+    env_path.join("conda-meta").is_dir()
+}
+
+/// Returns the variables of the conda environment at `prefix`.
+///
+/// conda stores them in the `env_vars` object of `conda-meta/state`, when the environment is
+/// created from an `environment.yml` with `variables` or with `conda env config vars set`.
+/// A missing or invalid state file has no variables.
+pub fn conda_env_vars(prefix: &Path) -> Vec<(String, OsString)> {
+    let Ok(content) = fs::read_to_string(prefix.join("conda-meta").join("state")) else {
+        return Vec::new();
+    };
+    let Ok(state) = serde_json::from_str::<serde_json::Value>(&content) else {
+        return Vec::new();
+    };
+    state
+        .get("env_vars")
+        .and_then(|env_vars| env_vars.as_object())
+        .map(|env_vars| {
+            env_vars
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.into())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the current `PATH` with `dirs` in front, removing their other occurrences.
+///
+/// If the result cannot be joined, because a directory contains the separator, the current
+/// `PATH` is returned.
+fn prepend_to_path(dirs: &[PathBuf]) -> OsString {
+    let current = env::var_os("PATH").unwrap_or_default();
+    let mut paths = dirs.to_vec();
+    if !current.is_empty() {
+        paths.extend(env::split_paths(&current).filter(|path| !dirs.contains(path)));
+    }
+    env::join_paths(paths).unwrap_or(current)
+}
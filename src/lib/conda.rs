@@ -0,0 +1,326 @@
+use crate::fingerprint::Fingerprint;
+use crate::{Error, Result, find_executable, warning_println};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The name of the file the fingerprint of the `environment.yml` is stored in, inside the
+/// conda environment.
+pub const ENVIRONMENT_FINGERPRINT_FILE_NAME: &str = "py_executer-environment-fingerprint";
+
+/// The name of the file marking a conda environment as created by py_executer.
+///
+/// Only these environments are pruned when they are updated, the packages installed by hand
+/// into an environment of the user are kept.
+pub const CREATED_MARKER_FILE_NAME: &str = "py_executer-created";
+
+/// The `environment.yml` of a project, describing a conda environment.
+///
+/// Only the top-level `name` and `prefix` are read, the rest of the file is passed to the
+/// conda tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The name of the environment.
+    pub name: Option<String>,
+    /// The path of the environment, used instead of the name if given.
+    pub prefix: Option<PathBuf>,
+}
+
+impl EnvironmentFile {
+    /// Finds the `environment.yml` or `environment.yaml` of the project at `runtime_path`.
+    pub fn find(runtime_path: &Path) -> Option<PathBuf> {
+        ["environment.yml", "environment.yaml"]
+            .into_iter()
+            .map(|file_name| runtime_path.join(file_name))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads the environment file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidEnvironmentFile`] if the file cannot be read or
+    /// has neither a `name` nor a `prefix`.
+    pub fn read(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidEnvironmentFile {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        let environment = EnvironmentFile::parse(path, &content);
+        if environment.name.is_none() && environment.prefix.is_none() {
+            return Err(invalid("it has no name or prefix".to_string()));
+        }
+        Ok(environment)
+    }
+
+    /// Parses the top-level `name` and `prefix` of the content of an environment file.
+    pub fn parse(path: &Path, content: &str) -> Self {
+        let value = |key: &str| {
+            content.lines().find_map(|line| {
+                // top-level keys are not indented
+                let value = line.strip_prefix(key)?.strip_prefix(':')?;
+                let value = value.split(" #").next().unwrap_or_default().trim();
+                let value = value.trim_matches(|c| c == '"' || c == '\'');
+                (!value.is_empty()).then(|| value.to_string())
+            })
+        };
+        EnvironmentFile {
+            path: path.to_path_buf(),
+            name: value("name"),
+            prefix: value("prefix").map(PathBuf::from),
+        }
+    }
+
+    /// Returns the fingerprint of the content of the file.
+    pub fn fingerprint(&self) -> String {
+        let mut fingerprint = Fingerprint::new();
+        fingerprint.file(&self.path);
+        fingerprint.finish()
+    }
+}
+
+/// The kind of a conda tool, they differ in their command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CondaKind {
+    /// `conda`
+    Conda,
+    /// `mamba`, a faster conda with the same command line.
+    Mamba,
+    /// `micromamba`, a standalone mamba.
+    Micromamba,
+}
+
+impl fmt::Display for CondaKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CondaKind::Conda => "conda",
+            CondaKind::Mamba => "mamba",
+            CondaKind::Micromamba => "micromamba",
+        })
+    }
+}
+
+/// A conda, mamba or micromamba executable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CondaTool {
+    /// The path of the executable.
+    pub path: PathBuf,
+    /// The kind of the tool.
+    pub kind: CondaKind,
+}
+
+impl CondaTool {
+    /// Finds a conda tool.
+    ///
+    /// `mamba`, `micromamba` and `conda` are looked up in `PATH` in this order, then the
+    /// executables in `MAMBA_EXE` and `CONDA_EXE`, set by the shell initialization of
+    /// micromamba and conda.
+    pub fn find() -> Option<Self> {
+        let in_path = [CondaKind::Mamba, CondaKind::Micromamba, CondaKind::Conda]
+            .into_iter()
+            .find_map(|kind| {
+                find_executable(&kind.to_string()).map(|path| CondaTool { path, kind })
+            });
+        in_path.or_else(|| {
+            [
+                ("MAMBA_EXE", CondaKind::Micromamba),
+                ("CONDA_EXE", CondaKind::Conda),
+            ]
+            .into_iter()
+            .find_map(|(key, kind)| {
+                let path = PathBuf::from(env::var_os(key)?);
+                path.is_file().then_some(CondaTool { path, kind })
+            })
+        })
+    }
+
+    /// Returns the path of the environment with the given name, if it exists.
+    ///
+    /// The environments are listed with `env list --json`, `base` is the root environment.
+    pub fn env_prefix(&self, name: &str) -> Option<PathBuf> {
+        let output = Command::new(&self.path)
+            .args(["env", "list", "--json"])
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let list: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+        let envs: Vec<PathBuf> = list
+            .get("envs")?
+            .as_array()?
+            .iter()
+            .filter_map(|env| env.as_str().map(PathBuf::from))
+            .collect();
+        let is_root = |env: &PathBuf| !env.parent().is_some_and(|parent| parent.ends_with("envs"));
+        envs.into_iter().find(|env| {
+            if name == "base" {
+                is_root(env)
+            } else {
+                !is_root(env) && env.file_name().is_some_and(|env_name| env_name == name)
+            }
+        })
+    }
+
+    /// Finds or creates the environment of `environment`, and updates it if the file changed
+    /// since it was created or last updated.
+    ///
+    /// The fingerprint of the file is stored in the environment, see
+    /// [`ENVIRONMENT_FINGERPRINT_FILE_NAME`]. An existing environment without a fingerprint
+    /// is updated once. Packages not in the file are only removed from environments created by
+    /// py_executer, see [`CREATED_MARKER_FILE_NAME`]. With `offline`, the tool only uses the
+    /// packages in its cache.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::VenvCreationFailed`] if the environment cannot be created,
+    /// [`Error::DependencySyncFailed`] if it cannot be updated, or [`Error::SpawnFailed`] if the
+    /// tool cannot be executed.
+    ///
+    /// # Returns
+    ///
+    /// The path of the environment.
+    pub fn prepare(
+        &self,
+        environment: &EnvironmentFile,
+        offline: bool,
+        quiet: bool,
+    ) -> Result<PathBuf> {
+        let existing = match (&environment.prefix, &environment.name) {
+            (Some(prefix), _) => Some(prefix.clone()).filter(|prefix| prefix.exists()),
+            (None, Some(name)) => self.env_prefix(name),
+            (None, None) => None,
+        };
+        let fingerprint = environment.fingerprint();
+        let prefix = match existing {
+            Some(prefix) => {
+                let stored =
+                    fs::read_to_string(prefix.join(ENVIRONMENT_FINGERPRINT_FILE_NAME)).ok();
+                if stored.as_deref().map(str::trim) == Some(fingerprint.as_str()) {
+                    return Ok(prefix);
+                }
+                if !quiet {
+                    println!(
+                        "Updating conda environment {} from {}",
+                        prefix.display(),
+                        environment.path.display()
+                    );
+                }
+                let mut cmd = self.command(offline);
+                match self.kind {
+                    CondaKind::Micromamba => cmd.args(["install", "--yes"]),
+                    _ => cmd.args(["env", "update"]),
+                };
+                // `env update` has no --offline, it only honours CONDA_OFFLINE and MAMBA_OFFLINE
+                if offline && self.kind == CondaKind::Micromamba {
+                    cmd.arg("--offline");
+                }
+                if self.kind != CondaKind::Micromamba
+                    && prefix.join(CREATED_MARKER_FILE_NAME).exists()
+                {
+                    cmd.arg("--prune");
+                }
+                cmd.arg("--prefix")
+                    .arg(&prefix)
+                    .arg("--file")
+                    .arg(&environment.path);
+                let tool = format!("{} env update", self.kind);
+                if let Err(stderr) = self.run(&mut cmd, quiet)? {
+                    return Err(Error::DependencySyncFailed { tool, stderr });
+                }
+                prefix
+            }
+            None => {
+                if !quiet {
+                    warning_println!(
+                        "No conda environment found for {}, will create it with {}",
+                        environment.path.display(),
+                        self.kind
+                    );
+                }
+                let mut cmd = self.command(offline);
+                match self.kind {
+                    CondaKind::Micromamba => cmd.args(["create", "--yes"]),
+                    _ => cmd.args(["env", "create"]),
+                };
+                // like `env update`, `env create` only honours CONDA_OFFLINE and MAMBA_OFFLINE
+                if offline && self.kind == CondaKind::Micromamba {
+                    cmd.arg("--offline");
+                }
+                match (&environment.prefix, &environment.name) {
+                    (Some(prefix), _) => cmd.arg("--prefix").arg(prefix),
+                    (None, Some(name)) => cmd.arg("--name").arg(name),
+                    (None, None) => &mut cmd,
+                };
+                cmd.arg("--file").arg(&environment.path);
+                let creation_failed = |stderr: String| Error::VenvCreationFailed {
+                    path: environment
+                        .prefix
+                        .clone()
+                        .unwrap_or_else(|| environment.path.clone()),
+                    stderr,
+                };
+                self.run(&mut cmd, quiet)?.map_err(creation_failed)?;
+                let prefix = environment
+                    .prefix
+                    .clone()
+                    .or_else(|| self.env_prefix(environment.name.as_deref()?))
+                    .ok_or_else(|| {
+                        creation_failed(format!("{} did not create the environment", self.kind))
+                    })?;
+                if let Err(err) = fs::write(prefix.join(CREATED_MARKER_FILE_NAME), "")
+                    && !quiet
+                {
+                    warning_println!("Failed to mark the environment as created: {}", err);
+                }
+                prefix
+            }
+        };
+        if let Err(err) = fs::write(prefix.join(ENVIRONMENT_FINGERPRINT_FILE_NAME), fingerprint)
+            && !quiet
+        {
+            warning_println!("Failed to store the environment fingerprint: {}", err);
+        }
+        Ok(prefix)
+    }
+
+    /// Returns a command of the tool, which does not access the network if `offline`.
+    fn command(&self, offline: bool) -> Command {
+        let mut cmd = Command::new(&self.path);
+        if offline {
+            cmd.env("CONDA_OFFLINE", "true")
+                .env("MAMBA_OFFLINE", "true");
+        }
+        cmd
+    }
+
+    /// Runs a command of the tool, showing its stdout unless `quiet`.
+    ///
+    /// Returns the stderr of the tool as error if it failed.
+    fn run(&self, cmd: &mut Command, quiet: bool) -> Result<std::result::Result<(), String>> {
+        let output = cmd
+            .stdout(if quiet {
+                Stdio::null()
+            } else {
+                Stdio::inherit()
+            })
+            .stderr(Stdio::piped())
+            .output()
+            .map_err(|source| Error::SpawnFailed {
+                program: self.kind.to_string(),
+                source,
+            })?;
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if !output.status.success() {
+            return Ok(Err(stderr));
+        }
+        if !quiet {
+            eprint!("{}", stderr);
+        }
+        Ok(Ok(()))
+    }
+}
@@ -0,0 +1,619 @@
+use crate::discovery::{InterpreterSource, probe_interpreter};
+use crate::fingerprint::Fingerprint;
+use crate::installer::run_installer;
+use crate::managers::PackageManager;
+use crate::path::VenvSource;
+use crate::pyproject::PyProject;
+use crate::script::ScriptMetadata;
+use crate::venv::ensure_pip;
+use crate::version::PythonRequest;
+use crate::workspace::{Workspace, WorkspaceMember};
+use crate::{Error, Result, find_executable, warning_println};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The extras and dependency groups of a uv project to sync.
+///
+/// They can be given on the command line or in the `[tool.py_executer]` table of the
+/// `pyproject.toml`:
+///
+/// ```toml
+/// [tool.py_executer]
+/// extras = ["cli"]
+/// groups = ["lint"]
+/// no-dev = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencySelection {
+    /// The optional dependencies to install, `uv sync --extra`.
+    pub extras: Vec<String>,
+    /// Installs all optional dependencies, `uv sync --all-extras`.
+    pub all_extras: bool,
+    /// The dependency groups to install in addition, `uv sync --group`.
+    pub groups: Vec<String>,
+    /// Does not install the `dev` group, `uv sync --no-dev`.
+    pub no_dev: bool,
+    /// Installs only these dependency groups, without the project, `uv sync --only-group`.
+    pub only_groups: Vec<String>,
+}
+
+impl DependencySelection {
+    /// Reads the selection from the `[tool.py_executer]` table of a `pyproject.toml`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidPyProject`] if a key has the wrong type.
+    pub fn from_pyproject(pyproject: &PyProject) -> Result<Self> {
+        let Some(config) = pyproject.tool_config() else {
+            return Ok(DependencySelection::default());
+        };
+        let invalid = |key: &str, expected: &str| Error::InvalidPyProject {
+            path: pyproject.path.clone(),
+            reason: format!("tool.py_executer.{} must be {}", key, expected),
+        };
+        let strings = |key: &str| match config.get(key) {
+            None => Ok(Vec::new()),
+            Some(value) => value
+                .as_array()
+                .and_then(|values| {
+                    values
+                        .iter()
+                        .map(|value| value.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| invalid(key, "an array of strings")),
+        };
+        let flag = |key: &str| match config.get(key) {
+            None => Ok(false),
+            Some(value) => value.as_bool().ok_or_else(|| invalid(key, "a boolean")),
+        };
+        Ok(DependencySelection {
+            extras: strings("extras")?,
+            all_extras: flag("all-extras")?,
+            groups: strings("groups")?,
+            no_dev: flag("no-dev")?,
+            only_groups: strings("only-groups")?,
+        })
+    }
+
+    /// Fills the parts not given in `self` from `config`.
+    ///
+    /// Lists given in `self` replace the ones of `config`, flags are enabled if they are
+    /// enabled in either. As `extras` and `all_extras` conflict, the one given in `self`
+    /// replaces the other one of `config`.
+    pub fn or(self, config: DependencySelection) -> Self {
+        let or_list = |list: Vec<String>, fallback: Vec<String>| {
+            if list.is_empty() { fallback } else { list }
+        };
+        let all_extras = self.all_extras || (self.extras.is_empty() && config.all_extras);
+        DependencySelection {
+            extras: if self.all_extras {
+                Vec::new()
+            } else {
+                or_list(self.extras, config.extras)
+            },
+            all_extras,
+            groups: or_list(self.groups, config.groups),
+            no_dev: self.no_dev || config.no_dev,
+            only_groups: or_list(self.only_groups, config.only_groups),
+        }
+    }
+
+    /// Returns `true` if nothing is selected, so the default set is installed.
+    pub fn is_empty(&self) -> bool {
+        *self == DependencySelection::default()
+    }
+
+    /// Returns the `uv sync` arguments of the selection.
+    pub fn sync_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for extra in &self.extras {
+            args.extend(["--extra".to_string(), extra.clone()]);
+        }
+        if self.all_extras {
+            args.push("--all-extras".to_string());
+        }
+        for group in &self.groups {
+            args.extend(["--group".to_string(), group.clone()]);
+        }
+        if self.no_dev {
+            args.push("--no-dev".to_string());
+        }
+        for group in &self.only_groups {
+            args.extend(["--only-group".to_string(), group.clone()]);
+        }
+        args
+    }
+}
+
+/// How the dependencies are resolved and downloaded, for reproducible installs.
+///
+/// With uv, the modes map onto the `uv sync` and `uv pip install` options of the same name.
+/// With the native pip, `offline` maps onto `--no-index`, and the `find_links` are the only
+/// package sources left.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncMode {
+    /// Fails if `uv.lock` is missing or out of date with `pyproject.toml`, `uv sync --locked`.
+    pub locked: bool,
+    /// Installs `uv.lock` as is, without checking it, `uv sync --frozen`.
+    pub frozen: bool,
+    /// Does not access the network, `--offline` with uv and `--no-index` with pip.
+    pub offline: bool,
+    /// Additional local directories or URLs to look for packages in, `--find-links`.
+    pub find_links: Vec<String>,
+}
+
+impl SyncMode {
+    /// Returns `true` if the mode requires an up to date or existing `uv.lock`.
+    pub fn uses_lock(&self) -> bool {
+        self.locked || self.frozen
+    }
+
+    /// Returns the `uv sync` arguments of the mode.
+    pub fn sync_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.locked {
+            args.push("--locked".to_string());
+        }
+        if self.frozen {
+            args.push("--frozen".to_string());
+        }
+        args.extend(self.index_args(true));
+        args
+    }
+
+    /// Returns the arguments restricting the package sources, for uv if `uv` is `true`,
+    /// otherwise for pip.
+    pub fn index_args(&self, uv: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.offline {
+            args.push(if uv { "--offline" } else { "--no-index" }.to_string());
+        }
+        for find_links in &self.find_links {
+            args.extend(["--find-links".to_string(), find_links.clone()]);
+        }
+        args
+    }
+}
+
+/// The dependency sources of a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// The absolute paths of the requirements files.
+    pub requirements: Vec<PathBuf>,
+    /// The absolute paths of the constraints files, applied to every `pip install`.
+    pub constraints: Vec<PathBuf>,
+    /// The dependencies from the inline metadata of a script, installed into the own venv of
+    /// the script, never together with a project.
+    pub script_dependencies: Vec<String>,
+    /// The extras and dependency groups of the project.
+    pub selection: DependencySelection,
+    /// The package manager of the project, if it is not managed with uv.
+    pub manager: Option<PackageManager>,
+}
+
+/// The venv the dependencies are installed into and the tools to install them with.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncTarget<'a> {
+    /// The project path, where `pyproject.toml` and `uv.lock` are looked up.
+    pub runtime_path: &'a Path,
+    /// The path of the uv executable, empty if uv is not installed.
+    pub uv_path: &'a str,
+    /// The path of the venv.
+    pub venv: &'a Path,
+    /// Where the venv comes from, if it was explicitly selected.
+    pub explicit_source: Option<VenvSource>,
+    /// The Python executable of the venv.
+    pub python_exec_path: &'a Path,
+    /// How the dependencies are resolved and downloaded.
+    pub mode: &'a SyncMode,
+    /// The uv workspace rooted at the project path, if there is one.
+    pub workspace: Option<&'a Workspace>,
+    /// The workspace member to sync, `uv sync --package`.
+    pub member: Option<&'a WorkspaceMember>,
+}
+
+impl Dependencies {
+    /// Resolves the dependency sources of a run.
+    ///
+    /// Relative `requirements` and `constraints` are resolved against the current directory.
+    /// If no requirements file is given, the `requirements.txt` of the project is used if it
+    /// exists. The `selection` is completed from the `[tool.py_executer]` table of the
+    /// `pyproject.toml` of the project, see [`DependencySelection::or`]. The package manager
+    /// of the project is detected with [`PackageManager::detect`].
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::DependencyFileNotFound`] if a given requirements or
+    /// constraints file does not exist, or [`Error::InvalidPyProject`] if the `pyproject.toml`
+    /// cannot be read or its configuration is invalid.
+    pub fn resolve(
+        runtime_path: &Path,
+        requirements: &[PathBuf],
+        constraints: &[PathBuf],
+        selection: DependencySelection,
+    ) -> Result<Self> {
+        let resolve_all = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| {
+                    path.canonicalize()
+                        .map_err(|source| Error::DependencyFileNotFound {
+                            path: path.clone(),
+                            source,
+                        })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let mut requirements = resolve_all(requirements)?;
+        if requirements.is_empty() {
+            let default_requirements = runtime_path.join("requirements.txt");
+            if default_requirements.exists() {
+                requirements.push(default_requirements);
+            }
+        }
+        let pyproject_path = runtime_path.join("pyproject.toml");
+        let selection = if pyproject_path.exists() {
+            selection.or(DependencySelection::from_pyproject(&PyProject::read(
+                &pyproject_path,
+            )?)?)
+        } else {
+            selection
+        };
+        Ok(Dependencies {
+            requirements,
+            constraints: resolve_all(constraints)?,
+            script_dependencies: Vec::new(),
+            selection,
+            manager: PackageManager::detect(runtime_path),
+        })
+    }
+
+    /// Returns the dependencies of a script with inline metadata, installed into the own venv
+    /// of the script.
+    pub fn for_script(metadata: &ScriptMetadata) -> Self {
+        Dependencies {
+            script_dependencies: metadata.dependencies.clone(),
+            ..Dependencies::default()
+        }
+    }
+
+    /// Computes the hash of the dependencies a venv is created for, used to key the venvs in
+    /// the central store.
+    ///
+    /// Unlike [`Dependencies::fingerprint`], it does not depend on the venv itself.
+    pub fn hash(&self, runtime_path: &Path, python_request: Option<&PythonRequest>) -> String {
+        let mut fingerprint = self.base_fingerprint(runtime_path);
+        fingerprint.value(
+            "python-request",
+            python_request
+                .map(|request| request.to_string())
+                .unwrap_or_default(),
+        );
+        fingerprint.finish()
+    }
+
+    /// Computes the fingerprint of everything the installation into `target` depends on.
+    ///
+    /// This covers the dependency files, the installer, the interpreter of the venv and the
+    /// dependencies from the inline metadata of the script.
+    pub fn fingerprint(&self, target: &SyncTarget) -> String {
+        let mut fingerprint = self.base_fingerprint(target.runtime_path);
+        fingerprint.value(
+            "installer",
+            if target.uv_path.is_empty() {
+                "pip"
+            } else {
+                "uv"
+            },
+        );
+        fingerprint.value(
+            "explicit-source",
+            target
+                .explicit_source
+                .map(|source| source.to_string())
+                .unwrap_or_default(),
+        );
+        if let Some(workspace) = target.workspace {
+            for member in &workspace.members {
+                fingerprint.file(&member.path.join("pyproject.toml"));
+            }
+        }
+        if let Some(member) = target.member {
+            fingerprint.value("package", &member.name);
+        }
+        // a locked run has to check the lock even if an unlocked sync already installed it
+        fingerprint
+            .value("locked", [target.mode.locked as u8])
+            .value("frozen", [target.mode.frozen as u8]);
+        match probe_interpreter(target.python_exec_path, InterpreterSource::Path) {
+            Some(interpreter) => fingerprint
+                .value("python", interpreter.version.to_string())
+                .value("implementation", interpreter.implementation)
+                .value(
+                    "executable",
+                    interpreter.executable.to_string_lossy().as_bytes(),
+                ),
+            None => fingerprint.value("python", "<unknown>"),
+        };
+        fingerprint.finish()
+    }
+
+    fn base_fingerprint(&self, runtime_path: &Path) -> Fingerprint {
+        let mut fingerprint = Fingerprint::new();
+        for file_name in ["pyproject.toml", "uv.lock"] {
+            fingerprint.file(&runtime_path.join(file_name));
+        }
+        if let Some(manager) = self.manager {
+            for file_name in manager.project_files() {
+                fingerprint.file(&runtime_path.join(file_name));
+            }
+        }
+        for requirements in &self.requirements {
+            fingerprint.file(requirements);
+        }
+        for constraints in &self.constraints {
+            fingerprint.value("constraints", constraints.to_string_lossy().as_bytes());
+            fingerprint.file(constraints);
+        }
+        for dependency in &self.script_dependencies {
+            fingerprint.value("script-dependency", dependency);
+        }
+        for arg in self.selection.sync_args() {
+            fingerprint.value("selection", arg);
+        }
+        fingerprint
+    }
+
+    /// Installs the dependencies into the venv of `target`.
+    ///
+    /// With uv, a `pyproject.toml` is synced with `uv sync` and the requirements files are
+    /// installed with `uv pip install`. Without uv, only the requirements files are installed
+    /// with the pip of the venv. The dependencies of the script are installed last.
+    ///
+    /// An explicitly selected venv is synced without removing the packages the project does not
+    /// need. A conda environment is never synced, the dependencies of the `pyproject.toml` are
+    /// installed with `uv pip install` instead.
+    ///
+    /// The constraints files are passed to every `pip install`. `uv sync` installs the locked
+    /// versions instead, so they do not apply to it.
+    ///
+    /// A project managed by Poetry, PDM or Pipenv is installed from its lock file instead, see
+    /// [`PackageManager`].
+    ///
+    /// In a uv workspace, only the [`SyncTarget::member`] and its dependencies are synced, or
+    /// the whole workspace if no member is selected.
+    ///
+    /// The [`SyncMode`] of the target is applied to every installation. With
+    /// [`SyncMode::locked`], `uv.lock` is checked against `pyproject.toml` before syncing.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::StaleLockfile`] if the lock mode requires a `uv.lock` that
+    /// is missing or out of date, [`Error::DependencySyncFailed`] if an installation fails, or
+    /// [`Error::SpawnFailed`] if the installer cannot be executed.
+    pub fn sync(&self, target: &SyncTarget, quiet: bool) -> Result<()> {
+        let project_config_path = target.runtime_path.join("pyproject.toml");
+        let syncs_project = !target.uv_path.is_empty()
+            && project_config_path.exists()
+            && !target.explicit_source.is_some_and(VenvSource::is_conda);
+        if target.mode.uses_lock() && !syncs_project && self.manager.is_none() && !quiet {
+            warning_println!(
+                "--locked and --frozen only apply to uv sync of a uv project, will install without a lock"
+            );
+        }
+        if let Some(manager) = self.manager {
+            if !self.selection.is_empty() && !quiet {
+                warning_println!(
+                    "Extras and dependency groups only apply to uv projects, will install all {} dependencies",
+                    manager
+                );
+            }
+            self.install_managed(manager, target, quiet)?;
+        } else if !target.uv_path.is_empty() {
+            if !project_config_path.exists()
+                && self.requirements.is_empty()
+                && self.script_dependencies.is_empty()
+            {
+                // both config are not exist
+                if !quiet {
+                    warning_println!(
+                        "No pyproject.toml or requirements.txt found, will not prepare dependencies"
+                    );
+                }
+                return Ok(());
+            }
+            if project_config_path.exists()
+                && target.explicit_source.is_some_and(VenvSource::is_conda)
+            {
+                let selection = &self.selection;
+                if (!selection.groups.is_empty() || !selection.only_groups.is_empty()) && !quiet {
+                    warning_println!(
+                        "Dependency groups are only installed with uv sync, will not install them"
+                    );
+                }
+                let mut cmd = self.pip_install(target, quiet)?;
+                cmd.arg("-r")
+                    .arg(target.member.map_or(project_config_path, |member| {
+                        member.path.join("pyproject.toml")
+                    }));
+                for extra in &selection.extras {
+                    cmd.arg("--extra").arg(extra);
+                }
+                if selection.all_extras {
+                    cmd.arg("--all-extras");
+                }
+                run_installer(&mut cmd, "uv pip install", quiet)?;
+            } else if project_config_path.exists() {
+                if !self.constraints.is_empty() && !quiet {
+                    warning_println!(
+                        "Constraints files do not apply to uv sync, use tool.uv.constraint-dependencies in pyproject.toml instead"
+                    );
+                }
+                check_lock(target)?;
+                let mut cmd = Command::new(target.uv_path);
+                cmd.arg("sync")
+                    .arg("--project")
+                    .arg(target.runtime_path)
+                    .env("UV_PROJECT_ENVIRONMENT", target.venv);
+                if target.explicit_source.is_some() {
+                    cmd.arg("--inexact");
+                }
+                if let Some(member) = target.member {
+                    cmd.arg("--package").arg(&member.name);
+                }
+                cmd.args(self.selection.sync_args())
+                    .args(target.mode.sync_args());
+                run_installer(&mut cmd, "uv sync", quiet)?;
+            }
+        } else if !self.selection.is_empty() && !quiet {
+            warning_println!("Extras and dependency groups need uv, will not install them");
+        }
+        if !self.requirements.is_empty() {
+            let mut cmd = self.pip_install(target, quiet)?;
+            for requirements in &self.requirements {
+                cmd.arg("-r").arg(requirements);
+            }
+            run_installer(&mut cmd, pip_tool(target), quiet)?;
+        }
+        if !self.script_dependencies.is_empty() {
+            let mut cmd = self.pip_install(target, quiet)?;
+            cmd.args(&self.script_dependencies);
+            run_installer(&mut cmd, pip_tool(target), quiet)?;
+        }
+        Ok(())
+    }
+
+    /// Installs the dependencies of a project managed by Poetry, PDM or Pipenv.
+    ///
+    /// The package manager is used if it is installed, unless packages must not be downloaded
+    /// from the index because of `--offline` or `--find-links`. Otherwise the dependencies are
+    /// exported with [`PackageManager::export_requirements`] to a requirements file in the venv
+    /// and installed with `pip install`.
+    fn install_managed(
+        &self,
+        manager: PackageManager,
+        target: &SyncTarget,
+        quiet: bool,
+    ) -> Result<()> {
+        let restricted_index = target.mode.offline || !target.mode.find_links.is_empty();
+        let install_command = find_executable(manager.tool_name())
+            .filter(|_| !restricted_index)
+            .and_then(|tool_path| {
+                manager.install_command(
+                    &tool_path,
+                    target.runtime_path,
+                    target.venv,
+                    target.python_exec_path,
+                )
+            });
+        if let Some(mut cmd) = install_command {
+            let tool = match manager {
+                PackageManager::Poetry => "poetry install",
+                PackageManager::Pdm => "pdm sync",
+                PackageManager::Pipenv => "pipenv sync",
+            };
+            run_installer(&mut cmd, tool, quiet)?;
+            return Ok(());
+        }
+
+        if !quiet {
+            println!(
+                "Installing the {} dependencies with {}",
+                manager,
+                pip_tool(target)
+            );
+        }
+        let requirements = manager.export_requirements(target.runtime_path)?;
+        let requirements_path = target
+            .venv
+            .join(format!("py_executer-{}-requirements.txt", manager));
+        fs::write(&requirements_path, requirements.join("\n")).map_err(|err| {
+            Error::DependencySyncFailed {
+                tool: pip_tool(target).to_string(),
+                stderr: format!("failed to write {}: {}", requirements_path.display(), err),
+            }
+        })?;
+        let mut cmd = self.pip_install(target, quiet)?;
+        cmd.arg("-r").arg(&requirements_path);
+        run_installer(&mut cmd, pip_tool(target), quiet)?;
+        Ok(())
+    }
+
+    /// Builds the `pip install` command of the venv, with the constraints files and the package
+    /// sources of the [`SyncMode`].
+    ///
+    /// With uv, it is `uv pip install --python <venv python>`, otherwise the pip of the venv,
+    /// bootstrapped if needed.
+    fn pip_install(&self, target: &SyncTarget, quiet: bool) -> Result<Command> {
+        let mut cmd = if !target.uv_path.is_empty() {
+            let mut cmd = Command::new(target.uv_path);
+            cmd.args(["pip", "install", "--python"])
+                .arg(target.python_exec_path);
+            cmd
+        } else {
+            // a venv generated by uv may have no pip in it
+            ensure_pip(target.python_exec_path, quiet)?;
+            let mut cmd = Command::new(target.python_exec_path);
+            cmd.args(["-m", "pip", "install"]);
+            cmd
+        };
+        for constraints in &self.constraints {
+            cmd.arg("-c").arg(constraints);
+        }
+        cmd.args(target.mode.index_args(!target.uv_path.is_empty()));
+        Ok(cmd)
+    }
+}
+
+/// Checks that `uv.lock` of the project can be used with the lock mode of `target`.
+///
+/// With [`SyncMode::frozen`], the lock only has to exist. With [`SyncMode::locked`], it also
+/// has to be up to date with `pyproject.toml`, checked with `uv lock --locked`.
+///
+/// # Errors
+///
+/// The function returns [`Error::StaleLockfile`] if the lock is missing or out of date, or
+/// [`Error::SpawnFailed`] if uv cannot be executed.
+fn check_lock(target: &SyncTarget) -> Result<()> {
+    if !target.mode.uses_lock() {
+        return Ok(());
+    }
+    let lock_path = target.runtime_path.join("uv.lock");
+    if !lock_path.exists() {
+        return Err(Error::StaleLockfile {
+            path: lock_path,
+            reason: "it does not exist".to_string(),
+        });
+    }
+    if !target.mode.locked {
+        return Ok(());
+    }
+    let output = Command::new(target.uv_path)
+        .args(["lock", "--locked", "--project"])
+        .arg(target.runtime_path)
+        .args(target.mode.index_args(true))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|source| Error::SpawnFailed {
+            program: "uv lock".to_string(),
+            source,
+        })?;
+    if !output.status.success() {
+        return Err(Error::StaleLockfile {
+            path: lock_path,
+            reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn pip_tool(target: &SyncTarget) -> &'static str {
+    if target.uv_path.is_empty() {
+        "pip install"
+    } else {
+        "uv pip install"
+    }
+}
@@ -0,0 +1,322 @@
+use crate::version::PythonVersion;
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Where an interpreter was found.
+///
+/// The variants are ordered by preference, interpreters on `PATH` are preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InterpreterSource {
+    /// A directory of the `PATH` environment variable.
+    Path,
+    /// The shims or versions directory of pyenv.
+    Pyenv,
+    /// A Python installation managed by uv.
+    Uv,
+    /// A well-known system directory like `/usr/bin`.
+    System,
+}
+
+impl fmt::Display for InterpreterSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InterpreterSource::Path => "PATH",
+            InterpreterSource::Pyenv => "pyenv",
+            InterpreterSource::Uv => "uv",
+            InterpreterSource::System => "system",
+        })
+    }
+}
+
+/// A probed Python interpreter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    /// The path the interpreter was found at.
+    pub path: PathBuf,
+    /// The resolved `sys.executable` of the interpreter.
+    pub executable: PathBuf,
+    /// The version of the interpreter.
+    pub version: PythonVersion,
+    /// The implementation name, e.g. `cpython` or `pypy`.
+    pub implementation: String,
+    /// The machine architecture, e.g. `x86_64` or `arm64`.
+    pub architecture: String,
+    /// Where the interpreter was found.
+    pub source: InterpreterSource,
+}
+
+impl fmt::Display for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}, {}) at {}",
+            self.implementation,
+            self.version,
+            self.architecture,
+            self.source,
+            self.path.display()
+        )
+    }
+}
+
+/// The script used to probe an interpreter, prints one value per line.
+const PROBE_SCRIPT: &str = "import os, platform, sys
+print(platform.python_version())
+print(sys.implementation.name)
+print(platform.machine())
+print(os.path.realpath(sys.executable))";
+
+/// Runs the interpreter at `path` to read its version, implementation and architecture.
+///
+/// Returns `None` if the interpreter cannot be executed or does not answer as expected,
+/// e.g. for a pyenv shim of a version that is not active.
+pub fn probe_interpreter(path: &Path, source: InterpreterSource) -> Option<Interpreter> {
+    let output = Command::new(path)
+        .args(["-c", PROBE_SCRIPT])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines().map(str::trim);
+    let version = lines.next()?.parse().ok()?;
+    let implementation = lines.next()?.to_string();
+    let architecture = lines.next()?.to_string();
+    let executable = PathBuf::from(lines.next()?);
+    Some(Interpreter {
+        path: path.to_path_buf(),
+        executable,
+        version,
+        implementation,
+        architecture,
+        source,
+    })
+}
+
+/// Enumerates the paths of candidate interpreters, without probing them.
+///
+/// The candidates are collected in the order of preference:
+///
+/// 1. `python3`, `python3.X`, `pypy3` and `pypy3.X` in the directories of `PATH`, in `PATH` order
+/// 2. the pyenv shims and the interpreters under `$PYENV_ROOT/versions`, newest first
+/// 3. the uv-managed interpreters under `$UV_PYTHON_INSTALL_DIR`, newest first
+/// 4. `/usr/local/bin`, `/usr/bin` and `/opt/homebrew/bin`
+///
+/// Paths resolving to the same file are only listed once.
+pub fn interpreter_candidates() -> Vec<(PathBuf, InterpreterSource)> {
+    let mut candidates = Vec::new();
+
+    if let Some(path) = env::var_os("PATH") {
+        for dir in env::split_paths(&path) {
+            push_dir_candidates(&mut candidates, &dir, InterpreterSource::Path);
+        }
+    }
+
+    if let Some(pyenv_root) = pyenv_root() {
+        push_dir_candidates(
+            &mut candidates,
+            &pyenv_root.join("shims"),
+            InterpreterSource::Pyenv,
+        );
+        for version_dir in sorted_subdirs(&pyenv_root.join("versions")) {
+            push_dir_candidates(
+                &mut candidates,
+                &bin_dir(&version_dir),
+                InterpreterSource::Pyenv,
+            );
+        }
+    }
+
+    if let Some(uv_dir) = uv_python_dir() {
+        for version_dir in sorted_subdirs(&uv_dir) {
+            push_dir_candidates(
+                &mut candidates,
+                &bin_dir(&version_dir),
+                InterpreterSource::Uv,
+            );
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    for dir in ["/usr/local/bin", "/usr/bin", "/opt/homebrew/bin"] {
+        push_dir_candidates(&mut candidates, Path::new(dir), InterpreterSource::System);
+    }
+
+    let mut seen = HashSet::new();
+    candidates
+        .retain(|(path, _)| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())));
+    candidates
+}
+
+/// Finds and probes all interpreters, ranked by preference.
+///
+/// See [`interpreter_candidates`] for the ranking. The candidates are probed in parallel.
+/// Interpreters whose resolved executable was already found under another path are only
+/// listed once.
+pub fn find_interpreters() -> Vec<Interpreter> {
+    let candidates = interpreter_candidates();
+    let probed: Vec<Option<Interpreter>> = thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .iter()
+            .map(|(path, source)| scope.spawn(move || probe_interpreter(path, *source)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().ok().flatten())
+            .collect()
+    });
+
+    let mut seen = HashSet::new();
+    probed
+        .into_iter()
+        .flatten()
+        .filter(|interpreter| seen.insert(interpreter.executable.clone()))
+        .collect()
+}
+
+/// Returns the first interpreter, in the order of preference, that satisfies `predicate`.
+///
+/// Candidates are probed lazily, so this is cheaper than [`find_interpreters`] when the
+/// preferred interpreter matches.
+pub fn find_interpreter<F>(mut predicate: F) -> Option<Interpreter>
+where
+    F: FnMut(&Interpreter) -> bool,
+{
+    interpreter_candidates()
+        .into_iter()
+        .filter_map(|(path, source)| probe_interpreter(&path, source))
+        .find(|interpreter| predicate(interpreter))
+}
+
+/// Pushes the interpreter executables found in `dir`.
+///
+/// `python3` comes first, then `python3.X` newest first, then the same for `pypy3`.
+fn push_dir_candidates(
+    candidates: &mut Vec<(PathBuf, InterpreterSource)>,
+    dir: &Path,
+    source: InterpreterSource,
+) {
+    let Ok(entries) = dir.read_dir() else {
+        return;
+    };
+    let mut names: Vec<(u8, PythonVersion, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rank = interpreter_name_rank(&name)?;
+            Some((rank.0, rank.1, name))
+        })
+        .collect();
+    names.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+    for (_, _, name) in names {
+        let path = dir.join(name);
+        if path.is_file() {
+            candidates.push((path, source));
+        }
+    }
+}
+
+/// Ranks an executable name, returns `None` if it is not an interpreter.
+///
+/// Unversioned names rank before versioned ones, CPython before PyPy.
+fn interpreter_name_rank(name: &str) -> Option<(u8, PythonVersion)> {
+    #[cfg(target_os = "windows")]
+    let name = name.strip_suffix(".exe")?;
+
+    for (index, prefix) in ["python3", "pypy3"].iter().enumerate() {
+        let Some(rest) = name.strip_prefix(prefix) else {
+            continue;
+        };
+        let index = index as u8 * 2;
+        if rest.is_empty() {
+            return Some((index, PythonVersion::new(3, 0, 0)));
+        }
+        let minor = rest.strip_prefix('.')?;
+        if minor.is_empty() || !minor.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        return Some((index + 1, PythonVersion::new(3, minor.parse().ok()?, 0)));
+    }
+
+    // on Windows, the interpreter is usually only named python.exe
+    #[cfg(target_os = "windows")]
+    if name == "python" {
+        return Some((0, PythonVersion::new(3, 0, 0)));
+    }
+    None
+}
+
+/// Returns the subdirectories of `dir`, sorted by the version in their name, newest first.
+fn sorted_subdirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = dir.read_dir() else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<(Option<PythonVersion>, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            // uv names its installations like cpython-3.12.1-linux-x86_64-gnu
+            let version = name
+                .split('-')
+                .find_map(|part| part.parse::<PythonVersion>().ok());
+            (version, path)
+        })
+        .collect();
+    dirs.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    dirs.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Returns the directory of the executables of a Python installation.
+fn bin_dir(installation: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        installation.to_path_buf()
+    } else {
+        installation.join("bin")
+    }
+}
+
+/// Returns the root directory of pyenv, `$PYENV_ROOT` or `~/.pyenv`.
+fn pyenv_root() -> Option<PathBuf> {
+    env::var_os("PYENV_ROOT")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".pyenv")))
+}
+
+/// Returns the directory of the uv-managed Python installations.
+///
+/// This is `$UV_PYTHON_INSTALL_DIR`, or the `uv/python` directory under the data directory,
+/// `$XDG_DATA_HOME` or `~/.local/share` on Unix-like systems and `%APPDATA%` on Windows.
+fn uv_python_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("UV_PYTHON_INSTALL_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    #[cfg(target_os = "windows")]
+    let data_dir = env::var_os("APPDATA").map(PathBuf::from);
+
+    #[cfg(not(target_os = "windows"))]
+    let data_dir = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".local").join("share")));
+
+    data_dir.map(|dir| dir.join("uv").join("python"))
+}
+
+/// Returns the home directory of the current user.
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let home = env::var_os("USERPROFILE");
+
+    #[cfg(not(target_os = "windows"))]
+    let home = env::var_os("HOME");
+
+    home.filter(|home| !home.is_empty()).map(PathBuf::from)
+}
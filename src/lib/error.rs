@@ -0,0 +1,120 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Errors returned by the library.
+///
+/// Each variant describes one kind of failure, so callers can match on it to decide
+/// about exit codes or retries instead of parsing messages.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The `uv` executable could not be found.
+    #[error("uv not installed")]
+    UvNotFound,
+
+    /// Neither `uv` nor a native Python interpreter could be found.
+    #[error("Failed to get any python executable")]
+    NoPythonFound,
+
+    /// The python request given with `--python` or found in the project cannot be parsed.
+    #[error("Invalid python request {request}: {reason}")]
+    InvalidPythonRequest { request: String, reason: String },
+
+    /// No interpreter satisfies the python request.
+    #[error("No python interpreter found for request {request}")]
+    NoMatchingPython { request: String },
+
+    /// A `pyproject.toml` cannot be read or parsed.
+    #[error("Failed to read {}: {reason}", path.display())]
+    InvalidPyProject { path: PathBuf, reason: String },
+
+    /// The uv workspace of the project is invalid, or the requested member is not part of it.
+    #[error("Invalid uv workspace {}: {reason}", path.display())]
+    InvalidWorkspace { path: PathBuf, reason: String },
+
+    /// The lock file of Poetry, PDM or Pipenv cannot be read or parsed.
+    #[error("Failed to read lock file {}: {reason}", path.display())]
+    InvalidLockfile { path: PathBuf, reason: String },
+
+    /// The `environment.yml` of a conda environment cannot be read or names no environment.
+    #[error("Invalid conda environment file {}: {reason}", path.display())]
+    InvalidEnvironmentFile { path: PathBuf, reason: String },
+
+    /// The inline script metadata of the script is invalid.
+    #[error("Invalid inline script metadata in {}: {reason}", path.display())]
+    InvalidScriptMetadata { path: PathBuf, reason: String },
+
+    /// The script to run does not exist or cannot be resolved.
+    #[error("Failed to get absolute path of script {}: {source}", path.display())]
+    ScriptNotFound { path: PathBuf, source: io::Error },
+
+    /// A requirements or constraints file does not exist or cannot be resolved.
+    #[error("Failed to get absolute path of dependency file {}: {source}", path.display())]
+    DependencyFileNotFound { path: PathBuf, source: io::Error },
+
+    /// The project directory does not exist or cannot be resolved.
+    #[error("Failed to get absolute path of project {}: {source}", path.display())]
+    ProjectNotFound { path: PathBuf, source: io::Error },
+
+    /// The selected virtual environment cannot be used.
+    #[error("Invalid venv {}: {reason}", path.display())]
+    InvalidVenv { path: PathBuf, reason: String },
+
+    /// The virtual environment is broken or does not match the requested Python.
+    #[error("Venv {} cannot be used, {reason}", path.display())]
+    BrokenVenv { path: PathBuf, reason: String },
+
+    /// Creating the virtual environment failed.
+    #[error("Failed to create venv at {}: {stderr}", path.display())]
+    VenvCreationFailed { path: PathBuf, stderr: String },
+
+    /// The registry of the venvs created by py_executer cannot be read or written.
+    #[error("Failed to access the venv registry {}: {reason}", path.display())]
+    InvalidRegistry { path: PathBuf, reason: String },
+
+    /// Another process held the lock longer than the timeout.
+    #[error(
+        "Timed out after {}s waiting for the lock {}, another py_executer process is still preparing the venv",
+        timeout.as_secs(),
+        path.display()
+    )]
+    LockTimeout { path: PathBuf, timeout: Duration },
+
+    /// A lock file cannot be created or locked.
+    #[error("Failed to lock {}: {source}", path.display())]
+    LockFailed { path: PathBuf, source: io::Error },
+
+    /// A locked or frozen sync needs a `uv.lock` that is missing or out of date.
+    #[error(
+        "Lockfile {} cannot be used, run `uv lock` to update it: {reason}",
+        path.display()
+    )]
+    StaleLockfile { path: PathBuf, reason: String },
+
+    /// Installing or syncing the dependencies failed.
+    ///
+    /// `stderr` is empty if the output of the installer was already shown live.
+    #[error("Failed to install dependencies with {tool}{}", output_details(stderr))]
+    DependencySyncFailed { tool: String, stderr: String },
+
+    /// A forwarded signal was received before the script started.
+    #[error("Interrupted by signal {signal} before the script started")]
+    Interrupted { signal: i32 },
+
+    /// A process could not be spawned or waited for.
+    #[error("Failed to execute {program}: {source}")]
+    SpawnFailed { program: String, source: io::Error },
+}
+
+/// Formats the captured output of a failed tool, appended to the error message.
+fn output_details(output: &str) -> String {
+    let output = output.trim_end();
+    if output.is_empty() {
+        String::new()
+    } else {
+        format!(":\n{}", output)
+    }
+}
+
+/// Result type of the library.
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,78 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The name of the file the fingerprint is stored in, inside the venv.
+pub const FINGERPRINT_FILE_NAME: &str = "py_executer-fingerprint";
+
+/// A hash of everything the installed dependencies of a venv depend on.
+///
+/// The dependencies only need to be synced again if the fingerprint changed since the last
+/// successful sync.
+///
+/// # Examples
+///
+/// ```
+/// use py_executer_lib::fingerprint::Fingerprint;
+///
+/// let mut fingerprint = Fingerprint::new();
+/// fingerprint.value("python", "3.12.1");
+/// let digest = fingerprint.finish();
+/// assert_eq!(digest.len(), 64);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprint {
+    hasher: Sha256,
+}
+
+impl Fingerprint {
+    /// Creates an empty fingerprint.
+    pub fn new() -> Self {
+        Fingerprint::default()
+    }
+
+    /// Adds a named value.
+    ///
+    /// The key and the value are length-prefixed, so different splits of the same bytes
+    /// do not collide.
+    pub fn value(&mut self, key: &str, value: impl AsRef<[u8]>) -> &mut Self {
+        for part in [key.as_bytes(), value.as_ref()] {
+            self.hasher.update((part.len() as u64).to_le_bytes());
+            self.hasher.update(part);
+        }
+        self
+    }
+
+    /// Adds the content of the file at `path`, keyed by its path.
+    ///
+    /// A missing file is recorded as missing, so creating or removing it changes the fingerprint.
+    pub fn file(&mut self, path: &Path) -> &mut Self {
+        let key = path.to_string_lossy();
+        match fs::read(path) {
+            Ok(content) => self.value(&key, content),
+            Err(_) => self.value(&key, "<missing>"),
+        }
+    }
+
+    /// Returns the fingerprint as a hex string.
+    pub fn finish(self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Reads the fingerprint of the last successful sync of the venv, if there is one.
+pub fn read_fingerprint(venv_path: &Path) -> Option<String> {
+    fs::read_to_string(venv_path.join(FINGERPRINT_FILE_NAME))
+        .ok()
+        .map(|fingerprint| fingerprint.trim().to_string())
+}
+
+/// Stores the fingerprint of a successful sync in the venv.
+pub fn write_fingerprint(venv_path: &Path, fingerprint: &str) -> io::Result<()> {
+    fs::write(venv_path.join(FINGERPRINT_FILE_NAME), fingerprint)
+}
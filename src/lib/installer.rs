@@ -0,0 +1,209 @@
+use crate::{Error, Result};
+use colored::Colorize;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The packages changed by an installer run.
+///
+/// It is parsed from the output of `uv sync`, `uv pip install` and `pip install`. A package
+/// that was both removed and added, e.g. because its version changed, is counted as updated.
+///
+/// # Examples
+///
+/// ```
+/// use py_executer_lib::installer::InstallSummary;
+///
+/// let summary = InstallSummary::parse(
+///     "Resolved 3 packages in 10ms\n - idna==3.6\n + idna==3.7\n + requests==2.32.3\n",
+/// );
+/// assert_eq!(summary.added, ["requests"]);
+/// assert_eq!(summary.updated, ["idna"]);
+/// assert_eq!(summary.to_string(), "1 added, 0 removed, 1 updated");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallSummary {
+    /// The packages that were not installed before.
+    pub added: Vec<String>,
+    /// The packages that are not installed anymore.
+    pub removed: Vec<String>,
+    /// The packages installed in another version or reinstalled.
+    pub updated: Vec<String>,
+}
+
+impl InstallSummary {
+    /// Parses the combined output of an installer.
+    ///
+    /// uv lists the changes as ` + name==version`, ` - name==version` and ` ~ name==version`,
+    /// pip reports them as `Successfully installed name-version ...` and
+    /// `Successfully uninstalled name-version`. Poetry and PDM report every change on a line
+    /// starting with `- Installing`, `- Updating`, `- Removing` or `✔ Install`, `✔ Update`,
+    /// `✔ Remove`. Other lines are ignored.
+    pub fn parse(output: &str) -> Self {
+        let mut summary = InstallSummary::default();
+        for line in output.lines().map(str::trim) {
+            if let Some(packages) = line.strip_prefix("Successfully installed ") {
+                summary
+                    .added
+                    .extend(packages.split_whitespace().map(pip_package_name));
+            } else if let Some(package) = line.strip_prefix("Successfully uninstalled ") {
+                summary.removed.push(pip_package_name(package.trim()));
+            } else if let Some((action, name)) = manager_change(line) {
+                match action {
+                    "Install" | "Installing" => summary.added.push(name),
+                    "Remove" | "Removing" => summary.removed.push(name),
+                    _ => summary.updated.push(name),
+                }
+            } else if let Some((marker, package)) = line.split_at_checked(2)
+                && let Some(name) = uv_package_name(package)
+            {
+                match marker {
+                    "+ " => summary.added.push(name),
+                    "- " => summary.removed.push(name),
+                    "~ " => summary.updated.push(name),
+                    _ => {}
+                }
+            }
+        }
+        let removed = std::mem::take(&mut summary.removed);
+        for name in removed {
+            match summary.added.iter().position(|added| *added == name) {
+                Some(index) => {
+                    summary.added.remove(index);
+                    summary.updated.push(name);
+                }
+                None => summary.removed.push(name),
+            }
+        }
+        summary
+    }
+
+    /// Returns `true` if no package was changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+impl fmt::Display for InstallSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("no changes");
+        }
+        write!(
+            f,
+            "{} added, {} removed, {} updated",
+            self.added.len(),
+            self.removed.len(),
+            self.updated.len()
+        )
+    }
+}
+
+/// Returns the name of a uv package line like `requests==2.32.3` or `project @ file:///...`.
+fn uv_package_name(package: &str) -> Option<String> {
+    let (name, _) = package
+        .split_once("==")
+        .or_else(|| package.split_once(" @ "))?;
+    let name = name.trim();
+    (!name.is_empty() && !name.contains(char::is_whitespace)).then(|| name.to_string())
+}
+
+/// Returns the action and package name of a Poetry or PDM change line, like
+/// `- Updating idna (3.6 -> 3.7)` or `✔ Install requests 2.32.3 successful`.
+fn manager_change(line: &str) -> Option<(&str, String)> {
+    let change = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("✔ "))?;
+    let mut words = change.split_whitespace();
+    let action = words.next()?;
+    let known = [
+        "Installing",
+        "Updating",
+        "Downgrading",
+        "Removing",
+        "Install",
+        "Update",
+        "Remove",
+    ];
+    if !known.contains(&action) {
+        return None;
+    }
+    Some((action, words.next()?.to_string()))
+}
+
+/// Returns the name of a pip package like `requests-2.32.3`.
+fn pip_package_name(package: &str) -> String {
+    package
+        .rsplit_once('-')
+        .map_or(package, |(name, _)| name)
+        .to_string()
+}
+
+/// Runs an installer command, streaming its output live unless `quiet`.
+///
+/// The stdout and stderr of the installer are forwarded line by line to the same streams of
+/// the current process. When it finished, a summary of the changed packages is printed, see
+/// [`InstallSummary`].
+///
+/// # Errors
+///
+/// The function returns [`Error::DependencySyncFailed`] if the installer fails, or
+/// [`Error::SpawnFailed`] if it cannot be executed. The error only carries the stderr of the
+/// installer if `quiet`, otherwise it was already streamed.
+pub fn run_installer(cmd: &mut Command, tool: &str, quiet: bool) -> Result<InstallSummary> {
+    let spawn_failed = |source| Error::SpawnFailed {
+        program: tool.to_string(),
+        source,
+    };
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(spawn_failed)?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (stdout, stderr) = thread::scope(|scope| {
+        let stdout = scope.spawn(|| stream_lines(stdout, io::stdout(), quiet));
+        let stderr = stream_lines(stderr, io::stderr(), quiet);
+        (stdout.join().unwrap_or_default(), stderr)
+    });
+    let status = child.wait().map_err(spawn_failed)?;
+    if !status.success() {
+        return Err(Error::DependencySyncFailed {
+            tool: tool.to_string(),
+            stderr: if quiet { stderr } else { String::new() },
+        });
+    }
+
+    let summary = InstallSummary::parse(&format!("{}\n{}", stdout, stderr));
+    if !quiet {
+        println!("{}: {}", tool.bold(), summary);
+    }
+    Ok(summary)
+}
+
+/// Reads `source` line by line until it is closed, writing every line to `sink` unless `quiet`.
+///
+/// Returns everything that was read.
+fn stream_lines(source: Option<impl Read>, mut sink: impl Write, quiet: bool) -> String {
+    let mut output = String::new();
+    let Some(source) = source else {
+        return output;
+    };
+    let mut reader = BufReader::new(source);
+    let mut line = Vec::new();
+    // read raw bytes, a line that is not valid UTF-8 must not stop the reading and block the
+    // installer on a full pipe
+    while reader
+        .read_until(b'\n', &mut line)
+        .is_ok_and(|read| read > 0)
+    {
+        if !quiet {
+            let _ = sink.write_all(&line);
+        }
+        output.push_str(&String::from_utf8_lossy(&line));
+        line.clear();
+    }
+    output
+}
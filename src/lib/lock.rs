@@ -0,0 +1,140 @@
+use crate::fingerprint::Fingerprint;
+use crate::store::cache_dir;
+use crate::{Error, Result};
+use std::env;
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default time to wait for a lock held by another process.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The interval in which a held lock is tried again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An advisory lock on a file, shared between processes.
+///
+/// The lock is released when the value is dropped, or by the operating system when the
+/// process exits, so a crashed process never leaves a stale lock behind.
+#[derive(Debug)]
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Acquires the lock on the file at `path`, creating it if needed.
+    ///
+    /// If another process holds the lock, `on_wait` is called once and the lock is tried again
+    /// until `timeout` has passed.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::LockTimeout`] if the lock is still held after `timeout`,
+    /// or [`Error::LockFailed`] if the lock file cannot be created or locked.
+    pub fn acquire(path: &Path, timeout: Duration, on_wait: impl FnOnce()) -> Result<Self> {
+        FileLock::acquire_with(path, timeout, on_wait, false)
+    }
+
+    /// Acquires a shared lock on the file at `path`, like [`FileLock::acquire`].
+    ///
+    /// Any number of processes can hold a shared lock at the same time, but not while another
+    /// process holds the exclusive lock.
+    ///
+    /// # Errors
+    ///
+    /// The function returns the errors of [`FileLock::acquire`].
+    pub fn acquire_shared(path: &Path, timeout: Duration, on_wait: impl FnOnce()) -> Result<Self> {
+        FileLock::acquire_with(path, timeout, on_wait, true)
+    }
+
+    fn acquire_with(
+        path: &Path,
+        timeout: Duration,
+        on_wait: impl FnOnce(),
+        shared: bool,
+    ) -> Result<Self> {
+        let failed = |source| Error::LockFailed {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(failed)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(failed)?;
+
+        let deadline = Instant::now() + timeout;
+        let mut on_wait = Some(on_wait);
+        loop {
+            let locked = if shared {
+                file.try_lock_shared()
+            } else {
+                file.try_lock()
+            };
+            match locked {
+                Ok(()) => {
+                    return Ok(FileLock {
+                        file,
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(source)) => return Err(failed(source)),
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::LockTimeout {
+                    path: path.to_path_buf(),
+                    timeout,
+                });
+            }
+            if let Some(on_wait) = on_wait.take() {
+                on_wait();
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Returns the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Returns the path of the lock file for `key`, e.g. a project or venv path.
+///
+/// The lock files are kept in the `locks` directory under the
+/// [`cache_dir`](crate::store::cache_dir), or under the temporary directory if it cannot be
+/// determined, so read-only projects can be locked as well.
+pub fn lock_path(key: &Path) -> PathBuf {
+    lock_file_path("lock", key)
+}
+
+/// Returns the path of the lock file held shared by the runs using the venv at `venv`.
+///
+/// The `--clean` cleanup only removes a venv if it can lock the file exclusively, i.e. no
+/// other run is using the venv.
+pub fn venv_use_lock_path(venv: &Path) -> PathBuf {
+    lock_file_path("use", venv)
+}
+
+fn lock_file_path(kind: &str, key: &Path) -> PathBuf {
+    let mut fingerprint = Fingerprint::new();
+    fingerprint.value(kind, key.to_string_lossy().as_bytes());
+    let name = format!("{}.lock", &fingerprint.finish()[..16]);
+    cache_dir()
+        .unwrap_or_else(|| env::temp_dir().join("py_executer"))
+        .join("locks")
+        .join(name)
+}
@@ -0,0 +1,375 @@
+use crate::{Error, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A package manager with its own lock file, used by a project instead of uv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    /// Poetry, with a `poetry.lock`.
+    Poetry,
+    /// PDM, with a `pdm.lock`.
+    Pdm,
+    /// Pipenv, with a `Pipfile` and usually a `Pipfile.lock`.
+    Pipenv,
+}
+
+impl fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.tool_name())
+    }
+}
+
+impl PackageManager {
+    /// Detects the package manager of the project at `runtime_path`.
+    ///
+    /// A `uv.lock` takes precedence, so `None` is returned if it exists. Otherwise the project
+    /// uses Poetry if it has a `poetry.lock`, PDM if it has a `pdm.lock` and Pipenv if it has a
+    /// `Pipfile.lock` or a `Pipfile`.
+    pub fn detect(runtime_path: &Path) -> Option<Self> {
+        if runtime_path.join("uv.lock").exists() {
+            return None;
+        }
+        [
+            (PackageManager::Poetry, "poetry.lock"),
+            (PackageManager::Pdm, "pdm.lock"),
+            (PackageManager::Pipenv, "Pipfile.lock"),
+            (PackageManager::Pipenv, "Pipfile"),
+        ]
+        .into_iter()
+        .find(|(_, file_name)| runtime_path.join(file_name).exists())
+        .map(|(manager, _)| manager)
+    }
+
+    /// Returns the name of the executable of the package manager.
+    pub fn tool_name(&self) -> &'static str {
+        match self {
+            PackageManager::Poetry => "poetry",
+            PackageManager::Pdm => "pdm",
+            PackageManager::Pipenv => "pipenv",
+        }
+    }
+
+    /// Returns the names of the files describing the dependencies of the project.
+    pub fn project_files(&self) -> &'static [&'static str] {
+        match self {
+            PackageManager::Poetry => &["poetry.lock"],
+            PackageManager::Pdm => &["pdm.lock"],
+            PackageManager::Pipenv => &["Pipfile", "Pipfile.lock"],
+        }
+    }
+
+    /// Returns the path of the lock file of the project, if it exists.
+    pub fn lock_path(&self, runtime_path: &Path) -> Option<PathBuf> {
+        let file_name = match self {
+            PackageManager::Poetry => "poetry.lock",
+            PackageManager::Pdm => "pdm.lock",
+            PackageManager::Pipenv => "Pipfile.lock",
+        };
+        Some(runtime_path.join(file_name)).filter(|path| path.exists())
+    }
+
+    /// Builds the command installing the locked dependencies into the venv with the package
+    /// manager at `tool_path`.
+    ///
+    /// The package managers install into the venv given in `VIRTUAL_ENV` instead of their own:
+    ///
+    /// * Poetry: `poetry install --no-interaction`
+    /// * PDM: `pdm sync --no-interaction`, with `PDM_PYTHON` set to the venv Python
+    /// * Pipenv: `pipenv sync --dev`
+    ///
+    /// Returns `None` for Pipenv without a `Pipfile.lock`, which cannot be synced.
+    pub fn install_command(
+        &self,
+        tool_path: &Path,
+        runtime_path: &Path,
+        venv: &Path,
+        python_exec_path: &Path,
+    ) -> Option<Command> {
+        let mut cmd = Command::new(tool_path);
+        cmd.current_dir(runtime_path)
+            .env("VIRTUAL_ENV", venv)
+            .env_remove("PYTHONHOME");
+        match self {
+            PackageManager::Poetry => {
+                cmd.args(["install", "--no-interaction"]);
+            }
+            PackageManager::Pdm => {
+                cmd.args(["sync", "--no-interaction"])
+                    .env("PDM_PYTHON", python_exec_path)
+                    .env("PDM_IGNORE_SAVED_PYTHON", "1");
+            }
+            PackageManager::Pipenv => {
+                self.lock_path(runtime_path)?;
+                cmd.args(["sync", "--dev"])
+                    .env("PIPENV_PIPFILE", runtime_path.join("Pipfile"))
+                    .env("PIPENV_VERBOSITY", "-1")
+                    .env_remove("PIPENV_IGNORE_VIRTUALENVS");
+            }
+        }
+        Some(cmd)
+    }
+
+    /// Exports the dependencies of the project as requirements, for uv or pip.
+    ///
+    /// The locked versions are pinned with `==`, with their extras and environment markers.
+    /// Packages from git, a URL or a local path are exported as direct references. Pipenv
+    /// projects without a `Pipfile.lock` export the unlocked requirements of the `Pipfile`.
+    /// The packages of all groups are exported, except the optional ones of Poetry.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidLockfile`] if the lock file cannot be read or parsed.
+    pub fn export_requirements(&self, runtime_path: &Path) -> Result<Vec<String>> {
+        let packages = match (self, self.lock_path(runtime_path)) {
+            (PackageManager::Poetry, Some(path)) => read_poetry_lock(&path, runtime_path)?,
+            (PackageManager::Pdm, Some(path)) => read_pdm_lock(&path, runtime_path)?,
+            (PackageManager::Pipenv, Some(path)) => read_pipfile_lock(&path, runtime_path)?,
+            (PackageManager::Pipenv, None) => {
+                read_pipfile(&runtime_path.join("Pipfile"), runtime_path)?
+            }
+            (_, None) => Vec::new(),
+        };
+        Ok(packages.iter().map(Requirement::to_string).collect())
+    }
+}
+
+/// A requirement exported from a lock file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Requirement {
+    name: String,
+    extras: Vec<String>,
+    /// The version specifier, e.g. `==2.32.3`.
+    specifier: String,
+    /// The URL of a direct reference, e.g. `git+https://...@<commit>`.
+    url: Option<String>,
+    markers: Option<String>,
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.extras.is_empty() {
+            write!(f, "[{}]", self.extras.join(","))?;
+        }
+        match &self.url {
+            Some(url) => write!(f, " @ {}", url)?,
+            None if self.specifier != "*" => f.write_str(&self.specifier)?,
+            None => {}
+        }
+        if let Some(markers) = &self.markers {
+            write!(f, " ; {}", markers)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a `file://` URL of a local path, relative to `runtime_path`.
+fn file_url(runtime_path: &Path, path: &str) -> String {
+    let path = runtime_path.join(path).to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        // a Windows path like C:/project
+        format!("file:///{}", path)
+    }
+}
+
+fn invalid_lockfile(path: &Path, reason: impl fmt::Display) -> Error {
+    Error::InvalidLockfile {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+fn read_toml(path: &Path) -> Result<toml::Table> {
+    fs::read_to_string(path)
+        .map_err(|err| invalid_lockfile(path, err))?
+        .parse::<toml::Table>()
+        .map_err(|err| invalid_lockfile(path, err))
+}
+
+/// Returns the `[[package]]` array of tables of a TOML lock file.
+fn toml_packages<'a>(path: &Path, table: &'a toml::Table) -> Result<Vec<&'a toml::Table>> {
+    match table.get("package") {
+        None => Ok(Vec::new()),
+        Some(packages) => packages
+            .as_array()
+            .and_then(|packages| packages.iter().map(toml::Value::as_table).collect())
+            .ok_or_else(|| invalid_lockfile(path, "package must be an array of tables")),
+    }
+}
+
+/// Returns the string value of `key` in `table`.
+fn toml_str<'a>(table: &'a toml::Table, key: &str) -> Option<&'a str> {
+    table.get(key)?.as_str()
+}
+
+/// Returns the strings of the array `key` in `table`, ignoring other values.
+fn toml_strings(table: &toml::Table, key: &str) -> Vec<String> {
+    table
+        .get(key)
+        .and_then(toml::Value::as_array)
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn read_poetry_lock(path: &Path, runtime_path: &Path) -> Result<Vec<Requirement>> {
+    let table = read_toml(path)?;
+    let mut requirements = Vec::new();
+    for package in toml_packages(path, &table)? {
+        if package.get("optional").and_then(toml::Value::as_bool) == Some(true) {
+            continue;
+        }
+        let (Some(name), Some(version)) = (toml_str(package, "name"), toml_str(package, "version"))
+        else {
+            return Err(invalid_lockfile(path, "package without name or version"));
+        };
+        let source = package.get("source").and_then(toml::Value::as_table);
+        let url = source.and_then(|source| {
+            let url = toml_str(source, "url")?;
+            match toml_str(source, "type")? {
+                "git" => Some(format!(
+                    "git+{}@{}",
+                    url,
+                    toml_str(source, "resolved_reference")
+                        .or_else(|| toml_str(source, "reference"))
+                        .unwrap_or("HEAD")
+                )),
+                "directory" | "file" => Some(file_url(runtime_path, url)),
+                "url" => Some(url.to_string()),
+                // a package from a private index is pinned like any other
+                _ => None,
+            }
+        });
+        requirements.push(Requirement {
+            name: name.to_string(),
+            specifier: format!("=={}", version),
+            url,
+            markers: toml_str(package, "markers").map(str::to_string),
+            ..Default::default()
+        });
+    }
+    Ok(requirements)
+}
+
+fn read_pdm_lock(path: &Path, runtime_path: &Path) -> Result<Vec<Requirement>> {
+    let table = read_toml(path)?;
+    let mut requirements = Vec::new();
+    for package in toml_packages(path, &table)? {
+        let Some(name) = toml_str(package, "name") else {
+            return Err(invalid_lockfile(path, "package without name"));
+        };
+        let url = if let Some(git) = toml_str(package, "git") {
+            let revision = toml_str(package, "revision")
+                .or_else(|| toml_str(package, "ref"))
+                .unwrap_or("HEAD");
+            Some(format!("git+{}@{}", git, revision))
+        } else if let Some(local) = toml_str(package, "path") {
+            Some(file_url(runtime_path, local))
+        } else {
+            toml_str(package, "url").map(str::to_string)
+        };
+        requirements.push(Requirement {
+            name: name.to_string(),
+            extras: toml_strings(package, "extras"),
+            specifier: toml_str(package, "version")
+                .map_or_else(|| "*".to_string(), |version| format!("=={}", version)),
+            url,
+            markers: toml_str(package, "marker").map(str::to_string),
+        });
+    }
+    Ok(requirements)
+}
+
+fn read_pipfile_lock(path: &Path, runtime_path: &Path) -> Result<Vec<Requirement>> {
+    let content = fs::read_to_string(path).map_err(|err| invalid_lockfile(path, err))?;
+    let lock: serde_json::Value =
+        serde_json::from_str(&content).map_err(|err| invalid_lockfile(path, err))?;
+    let mut requirements = Vec::new();
+    for section in ["default", "develop"] {
+        let Some(packages) = lock.get(section).and_then(serde_json::Value::as_object) else {
+            continue;
+        };
+        for (name, package) in packages {
+            let field = |key: &str| package.get(key).and_then(serde_json::Value::as_str);
+            let url = if let Some(git) = field("git") {
+                Some(format!("git+{}@{}", git, field("ref").unwrap_or("HEAD")))
+            } else if let Some(local) = field("path") {
+                Some(file_url(runtime_path, local))
+            } else {
+                field("file").map(str::to_string)
+            };
+            let extras = package
+                .get("extras")
+                .and_then(serde_json::Value::as_array)
+                .map(|extras| {
+                    extras
+                        .iter()
+                        .filter_map(|extra| extra.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            requirements.push(Requirement {
+                name: name.clone(),
+                extras,
+                specifier: field("version").unwrap_or("*").to_string(),
+                url,
+                markers: field("markers").map(str::to_string),
+            });
+        }
+    }
+    Ok(requirements)
+}
+
+fn read_pipfile(path: &Path, runtime_path: &Path) -> Result<Vec<Requirement>> {
+    let table = read_toml(path)?;
+    let mut requirements = Vec::new();
+    for section in ["packages", "dev-packages"] {
+        let Some(packages) = table.get(section).and_then(toml::Value::as_table) else {
+            continue;
+        };
+        for (name, package) in packages {
+            let requirement = match package {
+                toml::Value::String(specifier) => Requirement {
+                    name: name.clone(),
+                    specifier: specifier.clone(),
+                    ..Default::default()
+                },
+                toml::Value::Table(package) => {
+                    let url = if let Some(git) = toml_str(package, "git") {
+                        Some(match toml_str(package, "ref") {
+                            Some(reference) => format!("git+{}@{}", git, reference),
+                            None => format!("git+{}", git),
+                        })
+                    } else if let Some(local) = toml_str(package, "path") {
+                        Some(file_url(runtime_path, local))
+                    } else {
+                        toml_str(package, "file").map(str::to_string)
+                    };
+                    Requirement {
+                        name: name.clone(),
+                        extras: toml_strings(package, "extras"),
+                        specifier: toml_str(package, "version").unwrap_or("*").to_string(),
+                        url,
+                        markers: toml_str(package, "markers").map(str::to_string),
+                    }
+                }
+                _ => {
+                    return Err(invalid_lockfile(
+                        path,
+                        format!("{}.{} must be a string or a table", section, name),
+                    ));
+                }
+            };
+            requirements.push(requirement);
+        }
+    }
+    Ok(requirements)
+}
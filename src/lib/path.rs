@@ -1,131 +1,374 @@
-use crate::warning_println;
-use std::path::PathBuf;
+use crate::discovery::find_interpreter;
+use crate::registry::VenvRegistry;
+use crate::store::{is_writable, store_venv_path};
+use crate::venv::{VenvHealth, check_venv};
+use crate::version::PythonRequest;
+use crate::{Error, Result, get_python_exec_path, warning_println};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 /// Finds the native Python executable path.
 ///
-/// If `uv_path` is empty, it uses `which` or `where` command to find the native Python executable.
-/// If the command is successful, it returns the path of the Python executable.
-/// If the command is not successful, it returns an empty string.
+/// If `uv_path` is empty, it returns the path of the preferred interpreter found by
+/// [`find_interpreter`] that satisfies `python_request`, see
+/// [`interpreter_candidates`](crate::discovery::interpreter_candidates) for the order of
+/// preference. If no interpreter is found, it returns an empty string.
+///
+/// A path request is used as is if it exists, without discovery.
 ///
 /// If `uv_path` is not empty, it returns an empty string.
+pub fn get_python_native_path(uv_path: &str, python_request: Option<&PythonRequest>) -> String {
+    if !uv_path.is_empty() {
+        return "".to_string();
+    }
+    match python_request {
+        Some(PythonRequest::Path(path)) if path.exists() => path.to_string_lossy().to_string(),
+        Some(python_request) => find_interpreter(|interpreter| python_request.matches(interpreter))
+            .map(|interpreter| interpreter.path.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        None => find_interpreter(|_| true)
+            .map(|interpreter| interpreter.path.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Where an explicitly selected environment comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VenvSource {
+    /// The `--venv` argument.
+    Argument,
+    /// The `VIRTUAL_ENV` variable of an activated venv.
+    VirtualEnv,
+    /// The `CONDA_PREFIX` variable of an activated conda environment.
+    Conda,
+    /// The conda environment declared by the `environment.yml` of the project.
+    EnvironmentFile,
+}
+
+impl VenvSource {
+    /// Returns `true` if the environment is a conda environment, where the project is
+    /// installed with `pip install` instead of `uv sync`.
+    pub fn is_conda(self) -> bool {
+        matches!(self, VenvSource::Conda | VenvSource::EnvironmentFile)
+    }
+}
+
+impl fmt::Display for VenvSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VenvSource::Argument => "--venv",
+            VenvSource::VirtualEnv => "VIRTUAL_ENV",
+            VenvSource::Conda => "CONDA_PREFIX",
+            VenvSource::EnvironmentFile => "environment.yml",
+        })
+    }
+}
+
+/// Finds an explicitly selected environment.
 ///
-/// # Platform-specific
+/// The environment is looked up in the following order:
 ///
-/// On Unix-like systems, it uses `which` command.
+/// 1. the `venv` argument
+/// 2. the `VIRTUAL_ENV` variable, set by activating a venv
+/// 3. the `CONDA_PREFIX` variable, set by activating a conda environment other than `base`
 ///
-/// On Windows, it uses `where` command.
-pub fn get_python_native_path(uv_path: &String) -> String {
-    if uv_path.is_empty() {
-        #[cfg(not(target_os = "windows"))]
-        let find_executable = "which";
-
-        // For Windows
-        #[cfg(target_os = "windows")]
-        let find_executable = "where";
+/// An environment from the variables is ignored with a warning if it has no Python executable.
+///
+/// # Errors
+///
+/// The function returns [`Error::InvalidVenv`] if the `venv` argument does not exist or has
+/// no Python executable.
+///
+/// # Returns
+///
+/// The absolute path of the environment and where it comes from, or `None` if no environment
+/// is selected.
+pub fn find_explicit_venv(
+    venv: Option<&Path>,
+    quiet: bool,
+) -> Result<Option<(PathBuf, VenvSource)>> {
+    if let Some(venv) = venv {
+        let invalid = |reason: String| Error::InvalidVenv {
+            path: venv.to_path_buf(),
+            reason,
+        };
+        let venv_path = venv
+            .canonicalize()
+            .map_err(|err| invalid(err.to_string()))?;
+        let python_exec_path = get_python_exec_path(&venv_path);
+        if !python_exec_path.exists() {
+            return Err(invalid(format!(
+                "{} does not exist",
+                python_exec_path.display()
+            )));
+        }
+        return Ok(Some((venv_path, VenvSource::Argument)));
+    }
 
-        let output = Command::new(find_executable)
-            .arg("python3")
-            .output()
-            .unwrap();
-        if output.status.success() {
-            String::from_utf8(output.stdout)
-                .unwrap_or("".to_string())
-                .trim()
-                .to_string()
-        } else {
-            "".to_string()
+    let conda_prefix = env::var_os("CONDA_PREFIX")
+        .filter(|_| env::var("CONDA_DEFAULT_ENV").is_ok_and(|name| name != "base"));
+    let candidates = [
+        (env::var_os("VIRTUAL_ENV"), VenvSource::VirtualEnv),
+        (conda_prefix, VenvSource::Conda),
+    ];
+    for (venv, source) in candidates {
+        let Some(venv) = venv.filter(|venv| !venv.is_empty()) else {
+            continue;
+        };
+        let venv_path = PathBuf::from(venv);
+        if get_python_exec_path(&venv_path).exists() {
+            return Ok(Some((venv_path, source)));
+        }
+        if !quiet {
+            warning_println!(
+                "{} points to {} without a python executable, will not use it",
+                source,
+                venv_path.display()
+            );
         }
-    } else {
-        "".to_string()
     }
+    Ok(None)
+}
+
+/// Options for finding or creating the project venv.
+#[derive(Debug, Clone, Copy)]
+pub struct VenvOptions<'a> {
+    /// The path of the uv executable, empty if uv is not installed.
+    pub uv_path: &'a str,
+    /// The path of the native Python executable, used if uv is not installed.
+    pub python_native_path: &'a str,
+    /// The requested Python, passed to `uv venv --python` and checked for a found venv.
+    pub python_request: Option<&'a PythonRequest>,
+    /// If `true`, suppresses warnings and errors.
+    pub quiet: bool,
+    /// If `true`, will clean the created uv-managed .venv and config files after execution.
+    pub clean: bool,
+    /// If `true`, a broken venv is recreated instead of returning an error.
+    pub rebuild: bool,
+    /// If `true`, a new venv is created in the central store instead of the project.
+    pub central: bool,
+    /// If `true`, uv does not download a missing Python, `uv venv --offline`.
+    pub offline: bool,
+    /// The hash of the dependency inputs, part of the key of a venv in the central store.
+    pub dependency_hash: &'a str,
 }
 
 /// Finds a virtual environment path.
 ///
-/// # Errors
-///
-/// The function returns an `Err` if the provided venv path does not exist or if the Python executable
-/// under the venv path does not exist.
+/// A `venv` or `.venv` under the runtime path is used if it exists, otherwise a new `.venv`
+/// is created. A found venv is checked with [`check_venv`]. If it is broken, e.g. because its
+/// base interpreter was removed, it is recreated if [`VenvOptions::rebuild`] is `true`.
 ///
-/// # Platform-specific
+/// If [`VenvOptions::central`] is `true` or the runtime path is not writable, the venv is
+/// taken from the central store instead of creating a `.venv`, see [`store_venv_path`]. A
+/// broken venv in the store is always recreated, as it is only a cache.
 ///
-/// On Unix-like systems, it uses `which` command.
+/// # Errors
 ///
-/// On Windows, it uses `where` command.
+/// The function returns [`Error::BrokenVenv`] if the found venv is broken and should not be
+/// rebuilt, [`Error::VenvCreationFailed`] if creating a new venv fails, or
+/// [`Error::SpawnFailed`] if the venv creation command cannot be executed.
 ///
 /// # Arguments
 ///
 /// * `runtime_path`: The runtime path of the current directory.
-/// * `uv_path`: The path of the uv executable.
-/// * `python_native_path`: The path of the native Python executable.
-/// * `quiet`: If `true`, suppresses warnings and errors.
-/// * `clean`: If `true`, will clean the created uv-managed .venv and config files after execution.
+/// * `options`: How to find or create the venv.
 /// * `files_to_clean`: A vector of paths to clean.
 ///
 /// # Returns
 ///
 /// The path of the found virtual environment.
 pub fn get_venv_path(
-    runtime_path: PathBuf,
-    uv_path: String,
-    python_native_path: String,
-    quiet: bool,
-    clean: bool,
+    runtime_path: &Path,
+    options: &VenvOptions,
     files_to_clean: &mut Vec<PathBuf>,
-) -> PathBuf {
+) -> Result<PathBuf> {
     let possible_venv_dir_names = ["venv", ".venv"];
-    possible_venv_dir_names
+    match possible_venv_dir_names
         .iter()
         .map(|name| runtime_path.join(name))
         .find(|path| path.exists())
-        .unwrap_or_else(|| {
-            prepare_venv(
-                quiet,
-                &runtime_path,
-                &uv_path,
-                &python_native_path,
-                clean,
-                files_to_clean,
-            )
-        })
+    {
+        Some(venv_path) => match check_venv(&venv_path, options.python_request, true) {
+            VenvHealth::Healthy(_) => Ok(venv_path),
+            VenvHealth::Broken(reason) if options.rebuild => {
+                // the venv existed before, so it is not cleaned after the run
+                rebuild_venv(&venv_path, runtime_path, &reason, options)?;
+                Ok(venv_path)
+            }
+            VenvHealth::Broken(reason) => Err(Error::BrokenVenv {
+                path: venv_path,
+                reason: format!("{}, rebuild it with --rebuild-venv", reason),
+            }),
+        },
+        None if options.central || !is_writable(runtime_path) => {
+            match store_venv_path(runtime_path, options.dependency_hash) {
+                Some(venv_path) => prepare_store_venv(venv_path, runtime_path, options),
+                None => {
+                    if !options.quiet {
+                        warning_println!(
+                            "Failed to determine the central venv store, will use the project"
+                        );
+                    }
+                    prepare_venv(runtime_path, options, files_to_clean)
+                }
+            }
+        }
+        None => prepare_venv(runtime_path, options, files_to_clean),
+    }
+}
+
+/// Uses or creates the venv at `venv_path` in the central store, for the project or script at
+/// `runtime_path`.
+///
+/// Venvs in the store are shared between runs, so they are never cleaned after the run. A
+/// broken venv is rebuilt.
+///
+/// # Errors
+///
+/// The function returns [`Error::VenvCreationFailed`] if the venv cannot be created.
+pub fn prepare_store_venv(
+    venv_path: PathBuf,
+    runtime_path: &Path,
+    options: &VenvOptions,
+) -> Result<PathBuf> {
+    if venv_path.exists() {
+        if let VenvHealth::Broken(reason) = check_venv(&venv_path, options.python_request, true) {
+            rebuild_venv(&venv_path, runtime_path, &reason, options)?;
+        }
+        return Ok(venv_path);
+    }
+    if !options.quiet {
+        warning_println!(
+            "No venv found in the central store, will generate {}",
+            venv_path.display()
+        );
+    }
+    if let Some(parent) = venv_path.parent() {
+        fs::create_dir_all(parent).map_err(|err| Error::VenvCreationFailed {
+            path: venv_path.clone(),
+            stderr: err.to_string(),
+        })?;
+    }
+    create_venv(&venv_path, options)?;
+    record_venv(&venv_path, runtime_path, options.quiet);
+    Ok(venv_path)
+}
+
+/// Removes the broken venv at `venv_path` and creates it again.
+fn rebuild_venv(
+    venv_path: &Path,
+    runtime_path: &Path,
+    reason: &str,
+    options: &VenvOptions,
+) -> Result<()> {
+    if !options.quiet {
+        warning_println!(
+            "Venv {} cannot be used, {}, will rebuild it",
+            venv_path.display(),
+            reason
+        );
+    }
+    fs::remove_dir_all(venv_path).map_err(|err| Error::VenvCreationFailed {
+        path: venv_path.to_path_buf(),
+        stderr: err.to_string(),
+    })?;
+    create_venv(venv_path, options)?;
+    record_venv(venv_path, runtime_path, options.quiet);
+    Ok(())
+}
+
+/// Records a venv created for the project at `runtime_path` in the [`VenvRegistry`].
+///
+/// The venv works without the record, so a failure only prints a warning.
+fn record_venv(venv_path: &Path, runtime_path: &Path, quiet: bool) {
+    let result = VenvRegistry::update(|registry| {
+        registry.record_created(venv_path, runtime_path);
+        true
+    });
+    if let Err(err) = result
+        && !quiet
+    {
+        warning_println!("Failed to record the venv: {}", err);
+    }
 }
 
 fn prepare_venv(
-    quiet: bool,
-    runtime_path: &PathBuf,
-    uv_path: &String,
-    python_native_path: &String,
-    clean: bool,
+    runtime_path: &Path,
+    options: &VenvOptions,
     files_to_clean: &mut Vec<PathBuf>,
-) -> PathBuf {
-    if !quiet {
+) -> Result<PathBuf> {
+    if !options.quiet {
         warning_println!(
             "No venv found in {}, will generate one",
             runtime_path.display()
         );
     }
     let new_venv_path = runtime_path.join(".venv");
-    let _ = Command::new(if uv_path.is_empty() {
-        &python_native_path
-    } else {
-        &uv_path
-    })
-    .args(["venv", &new_venv_path.to_str().unwrap()])
-    .stdout(if quiet {
-        Stdio::null()
+    // a venv removed after the run is not recorded, even a partially created one is removed
+    if options.clean {
+        files_to_clean.push(new_venv_path.clone());
+    }
+    create_venv(&new_venv_path, options)?;
+    if !options.clean {
+        record_venv(&new_venv_path, runtime_path, options.quiet);
+    }
+    Ok(new_venv_path)
+}
+
+/// Creates a venv at `new_venv_path` with `uv venv --seed`, or with `python -m venv` if uv is
+/// not installed. Both include pip in the venv.
+fn create_venv(new_venv_path: &Path, options: &VenvOptions) -> Result<()> {
+    let VenvOptions {
+        uv_path,
+        python_native_path,
+        python_request,
+        quiet,
+        offline,
+        ..
+    } = *options;
+    let program = if uv_path.is_empty() {
+        python_native_path
     } else {
-        Stdio::inherit()
-    })
-    .stderr(if quiet {
-        Stdio::null()
+        uv_path
+    };
+    let mut cmd = Command::new(program);
+    if uv_path.is_empty() {
+        cmd.args(["-m", "venv"]).arg(new_venv_path);
     } else {
-        Stdio::inherit()
-    })
-    .output()
-    .unwrap();
-    if clean {
-        files_to_clean.push(new_venv_path.clone());
+        // seed pip, so the venv also works with the native python when uv is not available
+        cmd.args(["venv", "--seed"]).arg(new_venv_path);
+        if let Some(python_request) = python_request {
+            cmd.arg("--python").arg(python_request.to_string());
+        }
+        if offline {
+            cmd.arg("--offline");
+        }
+    }
+    let output = cmd
+        .stdout(if quiet {
+            Stdio::null()
+        } else {
+            Stdio::inherit()
+        })
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|source| Error::SpawnFailed {
+            program: program.to_string(),
+            source,
+        })?;
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if !output.status.success() {
+        return Err(Error::VenvCreationFailed {
+            path: new_venv_path.to_path_buf(),
+            stderr,
+        });
+    }
+    if !quiet {
+        eprint!("{}", stderr);
     }
-    new_venv_path
+    Ok(())
 }
@@ -0,0 +1,402 @@
+use std::io;
+use std::process::{Child, Command, ExitCode, ExitStatus};
+use std::time::Duration;
+
+/// The default time a child is given to exit after a forwarded signal before it is killed.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Returns the shell-style exit code of a finished process.
+///
+/// A normal exit returns the exit code of the process. On Unix-like systems, a process killed
+/// by a signal returns `128 + signum`, like shells do, e.g. `137` for `SIGKILL`.
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    match exit_signal(status) {
+        Some(signal) => 128 + signal,
+        None => 1,
+    }
+}
+
+/// Returns the signal that terminated the process, if any.
+///
+/// Always returns `None` on Windows.
+pub fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Converts an exit code into an [`ExitCode`] for the current process.
+///
+/// Codes that do not fit into the portable `0..=255` range are reported as `1`, so a failure
+/// is never turned into a success by truncation.
+pub fn to_process_exit_code(code: i32) -> ExitCode {
+    ExitCode::from(u8::try_from(code).unwrap_or(1))
+}
+
+/// Catches `SIGINT`, `SIGTERM` and `SIGHUP` sent to the current process until dropped.
+///
+/// The signals are recorded instead of terminating the process, so it can finish its work, e.g.
+/// clean up, and check [`SignalGuard::received`] afterwards. Guards can overlap, also with the
+/// ones of [`ForwardingChild`], the previous signal handlers are restored when the last guard is
+/// dropped.
+///
+/// On Windows, no signal is caught.
+pub struct SignalGuard {
+    #[cfg(unix)]
+    guard: unix::SignalGuard,
+}
+
+impl SignalGuard {
+    /// Installs the signal handlers, or shares the ones of an existing guard.
+    pub fn install() -> Self {
+        SignalGuard {
+            #[cfg(unix)]
+            guard: unix::SignalGuard::install(),
+        }
+    }
+
+    /// Returns the last signal received since the guard was installed, if any.
+    pub fn received(&self) -> Option<i32> {
+        #[cfg(unix)]
+        {
+            self.guard.received()
+        }
+
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+}
+
+/// A child process that receives the termination signals of the current process.
+///
+/// On Unix-like systems, the child is spawned as the leader of its own process group. If the
+/// current process owns the terminal, the terminal is handed over to the child, so `Ctrl-C`
+/// and `Ctrl-Z` reach it like in a shell. `SIGINT`, `SIGTERM` and `SIGHUP` sent to the current
+/// process are caught and forwarded to the whole process group of the child while waiting for
+/// it, so grandchildren receive them too. If the child has not exited when the grace period
+/// after the first forwarded signal is over, the process group is killed with `SIGKILL`.
+///
+/// The terminal is restored when the value is dropped, and the previous signal handlers once no
+/// other [`SignalGuard`] is left, see there.
+///
+/// On Windows, the child is spawned and waited for without further handling.
+pub struct ForwardingChild {
+    child: Child,
+    grace_period: Duration,
+    #[cfg(unix)]
+    guard: unix::SignalGuard,
+}
+
+impl ForwardingChild {
+    /// Spawns the command with signal forwarding.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an `Err` if the command cannot be spawned.
+    pub fn spawn(command: &mut Command) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            let guard = unix::SignalGuard::install();
+            let child = unix::spawn_in_own_group(command, guard.owns_terminal())?;
+            guard.give_terminal_to(child.id());
+            Ok(ForwardingChild {
+                child,
+                grace_period: DEFAULT_GRACE_PERIOD,
+                guard,
+            })
+        }
+
+        #[cfg(not(unix))]
+        {
+            let child = command.spawn()?;
+            Ok(ForwardingChild {
+                child,
+                grace_period: DEFAULT_GRACE_PERIOD,
+            })
+        }
+    }
+
+    /// Sets the time the child is given to exit after a forwarded signal, before it is killed.
+    ///
+    /// Defaults to [`DEFAULT_GRACE_PERIOD`].
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Returns the process id of the child.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Waits for the child to exit, forwarding the received signals to it.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an `Err` if waiting for the child fails.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        #[cfg(unix)]
+        {
+            unix::wait_forwarding(&mut self.child, &self.guard, self.grace_period)
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = self.grace_period;
+            self.child.wait()
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use libc::c_int;
+    use std::io;
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::process::{Child, Command, ExitStatus};
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+    use std::sync::{Mutex, PoisonError};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Signals that are caught and forwarded to the child.
+    const FORWARDED_SIGNALS: [c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+    /// How often the child is polled while waiting.
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// The last signal received, `0` if none.
+    static LAST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+    /// The number of signals received, each guard compares it with the number at its install.
+    static SIGNAL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// The handlers installed by the live guards.
+    static HANDLERS: Mutex<Option<Handlers>> = Mutex::new(None);
+
+    struct Handlers {
+        /// The number of live guards.
+        guards: usize,
+        /// The actions to restore once the last guard is dropped.
+        previous: Vec<(c_int, libc::sigaction)>,
+    }
+
+    extern "C" fn record_signal(signal: c_int) {
+        LAST_SIGNAL.store(signal, Ordering::SeqCst);
+        SIGNAL_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Installs the forwarding signal handlers, shared by all live guards, and restores the
+    /// previous ones when the last guard is dropped.
+    pub(super) struct SignalGuard {
+        installed_at: usize,
+        owns_terminal: bool,
+    }
+
+    impl SignalGuard {
+        pub(super) fn install() -> Self {
+            let mut handlers = HANDLERS.lock().unwrap_or_else(PoisonError::into_inner);
+            match handlers.as_mut() {
+                Some(handlers) => handlers.guards += 1,
+                None => {
+                    let previous = FORWARDED_SIGNALS
+                        .iter()
+                        .map(|&signal| {
+                            (
+                                signal,
+                                set_handler(
+                                    signal,
+                                    record_signal as *const () as libc::sighandler_t,
+                                ),
+                            )
+                        })
+                        .collect();
+                    *handlers = Some(Handlers {
+                        guards: 1,
+                        previous,
+                    });
+                }
+            }
+            // SAFETY: plain libc queries on the stdin file descriptor.
+            let owns_terminal = unsafe {
+                libc::isatty(libc::STDIN_FILENO) == 1
+                    && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+            };
+            SignalGuard {
+                installed_at: SIGNAL_COUNT.load(Ordering::SeqCst),
+                owns_terminal,
+            }
+        }
+
+        pub(super) fn received(&self) -> Option<c_int> {
+            (SIGNAL_COUNT.load(Ordering::SeqCst) != self.installed_at)
+                .then(|| LAST_SIGNAL.load(Ordering::SeqCst))
+        }
+
+        pub(super) fn owns_terminal(&self) -> bool {
+            self.owns_terminal
+        }
+
+        /// Makes the process group of `pid` the foreground group of the terminal.
+        pub(super) fn give_terminal_to(&self, pid: u32) {
+            if self.owns_terminal {
+                set_foreground_group(pid as libc::pid_t);
+            }
+        }
+
+        /// Makes the process group of the current process the foreground group again.
+        pub(super) fn reclaim_terminal(&self) {
+            if self.owns_terminal {
+                // SAFETY: getpgrp cannot fail.
+                set_foreground_group(unsafe { libc::getpgrp() });
+            }
+        }
+    }
+
+    impl Drop for SignalGuard {
+        fn drop(&mut self) {
+            self.reclaim_terminal();
+            let mut handlers = HANDLERS.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some(installed) = handlers.as_mut() else {
+                return;
+            };
+            installed.guards -= 1;
+            if installed.guards > 0 {
+                return;
+            }
+            for (signal, action) in &installed.previous {
+                // SAFETY: restores an action previously returned by sigaction.
+                unsafe {
+                    libc::sigaction(*signal, action, std::ptr::null_mut());
+                }
+            }
+            *handlers = None;
+        }
+    }
+
+    /// Sets the handler of `signal` and returns the previous action.
+    fn set_handler(signal: c_int, handler: libc::sighandler_t) -> libc::sigaction {
+        // SAFETY: the action is fully initialized and the handler only stores to an atomic.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler;
+            libc::sigemptyset(&mut action.sa_mask);
+            let mut previous: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(signal, &action, &mut previous);
+            previous
+        }
+    }
+
+    /// Sets the foreground process group of the terminal, even from a background group.
+    fn set_foreground_group(pgid: libc::pid_t) {
+        // SAFETY: SIGTTOU is ignored while changing the foreground group, then restored.
+        unsafe {
+            let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+            libc::signal(libc::SIGTTOU, previous);
+        }
+    }
+
+    /// Spawns the command as the leader of a new process group.
+    ///
+    /// If `foreground` is `true`, the child also takes over the terminal before executing,
+    /// so it never runs as a background job.
+    pub(super) fn spawn_in_own_group(command: &mut Command, foreground: bool) -> io::Result<Child> {
+        // SAFETY: only async-signal-safe functions are called between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if foreground {
+                    libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                    libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpid());
+                    libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+                }
+                Ok(())
+            });
+        }
+        command.spawn()
+    }
+
+    /// Sends `signal` to the process group led by `pgid`.
+    fn send_group_signal(pgid: libc::pid_t, signal: c_int) {
+        // SAFETY: kill has no memory safety requirements.
+        unsafe {
+            libc::kill(-pgid, signal);
+        }
+    }
+
+    /// Waits for the child to exit, forwarding pending signals to its process group.
+    ///
+    /// If the child has not exited `grace_period` after the first forwarded signal, its process
+    /// group is killed. Once the child exited after a forwarded signal, the remaining processes
+    /// of its group are killed as well.
+    ///
+    /// If the child is stopped from the terminal, the current process stops as well and
+    /// continues the child when it is continued itself.
+    pub(super) fn wait_forwarding(
+        child: &mut Child,
+        guard: &SignalGuard,
+        grace_period: Duration,
+    ) -> io::Result<ExitStatus> {
+        let pid = child.id() as libc::pid_t;
+        let mut deadline: Option<Instant> = None;
+        let mut forwarded = guard.installed_at;
+        loop {
+            let received = SIGNAL_COUNT.load(Ordering::SeqCst);
+            if received != forwarded {
+                forwarded = received;
+                send_group_signal(pid, LAST_SIGNAL.load(Ordering::SeqCst));
+                deadline.get_or_insert_with(|| Instant::now() + grace_period);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                send_group_signal(pid, libc::SIGKILL);
+            }
+
+            let mut status: c_int = 0;
+            // SAFETY: waits for our own child, status is a valid pointer.
+            let result =
+                unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG | libc::WUNTRACED) };
+            if result == -1 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if result == pid {
+                if libc::WIFSTOPPED(status) {
+                    // job control: stop with the child, resume it when continued
+                    guard.reclaim_terminal();
+                    // SAFETY: stops the current process until it is continued.
+                    unsafe {
+                        libc::raise(libc::SIGTSTP);
+                    }
+                    guard.give_terminal_to(child.id());
+                    send_group_signal(pid, libc::SIGCONT);
+                    continue;
+                }
+                if deadline.is_some() {
+                    // the child is gone, do not leave its group behind
+                    send_group_signal(pid, libc::SIGKILL);
+                }
+                return Ok(ExitStatus::from_raw(status));
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::{Path, PathBuf};
+
+/// The files and directories that mark the root of a project, in order of preference.
+pub const PROJECT_MARKERS: [&str; 8] = [
+    "pyproject.toml",
+    "requirements.txt",
+    "Pipfile",
+    "environment.yml",
+    "environment.yaml",
+    ".venv",
+    "venv",
+    ".git",
+];
+
+/// Finds the root of the project a script belongs to.
+///
+/// The directories from `script_dir` up to the file system root are searched for one of the
+/// [`PROJECT_MARKERS`], the nearest directory containing any of them is the project root.
+///
+/// # Returns
+///
+/// The project root and the marker found in it, or `None` if no directory has a marker.
+///
+/// # Examples
+///
+/// ```no_run
+/// use py_executer_lib::project::find_project_root;
+/// use std::path::Path;
+///
+/// if let Some((root, marker)) = find_project_root(Path::new("/repo/tools")) {
+///     println!("{} contains {}", root.display(), marker);
+/// }
+/// ```
+pub fn find_project_root(script_dir: &Path) -> Option<(PathBuf, &'static str)> {
+    script_dir.ancestors().find_map(|dir| {
+        PROJECT_MARKERS
+            .into_iter()
+            .find(|marker| dir.join(marker).exists())
+            .map(|marker| (dir.to_path_buf(), marker))
+    })
+}
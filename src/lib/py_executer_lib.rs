@@ -1,26 +1,88 @@
+pub mod activation;
+pub mod conda;
+pub mod deps;
+pub mod discovery;
+pub mod error;
+pub mod fingerprint;
+pub mod installer;
+pub mod lock;
 pub mod macros;
+pub mod managers;
 pub mod path;
+pub mod process;
+pub mod project;
+pub mod pyproject;
+pub mod registry;
+pub mod runner;
+pub mod script;
+pub mod store;
+pub mod venv;
+pub mod version;
+pub mod workspace;
+
+pub use error::{Error, Result};
 
-use anyhow::anyhow;
 use colored::*;
 use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::{env, path::PathBuf};
-
-/// Append the current working directory to the `PYTHONPATH` environment variable.
-///
-/// The function takes a `PathBuf` as its argument, which represents the current working directory.
-/// It returns a `HashMap` where the key is the name of the environment variable and the value is
-/// the value of the environment variable.
-fn append_pwd_to_pythonpath(runtime_path: &PathBuf) -> HashMap<String, String> {
-    let mut path = env::var("PYTHONPATH").unwrap_or_default();
-    if !path.contains(&runtime_path.to_string_lossy().to_string()) {
-        if !path.is_empty() {
-            path.push(':');
+
+/// Returns the entries added to `PYTHONPATH` for the project at `runtime_path`.
+///
+/// The entries are the project itself, its `src` directory if the project has a src layout,
+/// then the `extra` entries. Relative `extra` entries are resolved against the current
+/// directory.
+pub fn pythonpath_roots(runtime_path: &Path, extra: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots = vec![runtime_path.to_path_buf()];
+    let src_path = runtime_path.join("src");
+    if has_python_sources(&src_path) {
+        roots.push(src_path);
+    }
+    roots.extend(
+        extra
+            .iter()
+            .map(|path| std::path::absolute(path).unwrap_or_else(|_| path.clone())),
+    );
+    roots
+}
+
+/// Returns `true` if `dir` contains a Python module or a package with an `__init__.py`.
+fn has_python_sources(dir: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let path = entry.path();
+        path.join("__init__.py").exists() || path.extension().is_some_and(|ext| ext == "py")
+    })
+}
+
+/// Appends `roots` to the `pythonpath` value, skipping the entries already in it.
+///
+/// The entries are split and joined with the separator of the platform, `:` on Unix-like
+/// systems and `;` on Windows, and compared as paths, so `/a/b` is not considered present
+/// because of `/a/bc`.
+///
+/// # Errors
+///
+/// The function returns an error if an entry contains the separator.
+pub fn append_to_pythonpath(
+    pythonpath: Option<&OsStr>,
+    roots: &[PathBuf],
+) -> std::result::Result<OsString, env::JoinPathsError> {
+    let mut paths: Vec<PathBuf> = pythonpath
+        .filter(|pythonpath| !pythonpath.is_empty())
+        .map(|pythonpath| env::split_paths(pythonpath).collect())
+        .unwrap_or_default();
+    for root in roots {
+        if !paths.contains(root) {
+            paths.push(root.clone());
         }
-        path.push_str(runtime_path.to_string_lossy().to_string().as_str());
     }
-    HashMap::from([("PYTHONPATH".to_string(), path)])
+    env::join_paths(paths)
 }
 
 /// Set additional environment variables from command line arguments.
@@ -31,16 +93,31 @@ fn append_pwd_to_pythonpath(runtime_path: &PathBuf) -> HashMap<String, String> {
 ///
 /// If a key-value pair is malformed, the function will print a warning message and ignore the pair.
 ///
-/// The function also adds the current working directory to the `PYTHONPATH` environment variable.
+/// The function also appends the `pythonpath_roots` to the `PYTHONPATH` environment variable, see
+/// [`append_to_pythonpath`]. If they are empty, `PYTHONPATH` is left as is.
 pub fn set_additional_env_var(
     additional_env_from_args: Vec<String>,
-    runtime_path: &PathBuf,
+    pythonpath_roots: &[PathBuf],
     quiet: bool,
 ) -> HashMap<String, String> {
     let mut additional_env = HashMap::new();
 
-    //add current dir to PYTHONPATH
-    additional_env.extend(append_pwd_to_pythonpath(runtime_path));
+    // add the project roots to PYTHONPATH
+    if !pythonpath_roots.is_empty() {
+        match append_to_pythonpath(env::var_os("PYTHONPATH").as_deref(), pythonpath_roots) {
+            Ok(pythonpath) => {
+                additional_env.insert(
+                    "PYTHONPATH".to_string(),
+                    pythonpath.to_string_lossy().to_string(),
+                );
+            }
+            Err(err) => {
+                if !quiet {
+                    warning_println!("Failed to extend PYTHONPATH, will not change it: {}", err);
+                }
+            }
+        }
+    }
 
     for env_var in additional_env_from_args {
         if let Some(pos) = env_var.find('=') {
@@ -50,13 +127,11 @@ pub fn set_additional_env_var(
             if !quiet {
                 println!("Setting env: {} = {}", key.bold(), value);
             }
-        } else {
-            if !quiet {
-                warning_println!(
-                    "Warning: Ignoring malformed environment variable: {}",
-                    env_var.bold()
-                );
-            }
+        } else if !quiet {
+            warning_println!(
+                "Warning: Ignoring malformed environment variable: {}",
+                env_var.bold()
+            );
         }
     }
     additional_env
@@ -71,34 +146,30 @@ pub fn set_additional_env_var(
 ///
 /// # Errors
 ///
-/// The function returns an `Err` if the path does not exist or if the parent directory
-/// cannot be obtained.
-pub fn validate_to_absolute_path(script_path: &PathBuf) -> anyhow::Result<PathBuf> {
-    match script_path.canonicalize() {
-        Ok(path) => {
-            if !path.exists() {
-                return Err(anyhow!("{} not exists", path.display().to_string().bold()));
-            }
-            Ok(path)
-        }
-        Err(err) => Err(anyhow!("Failed to get absolute path of script: {}", err)),
-    }
+/// The function returns [`Error::ScriptNotFound`] if the path does not exist or if the
+/// parent directory cannot be obtained.
+pub fn validate_to_absolute_path(script_path: &Path) -> Result<PathBuf> {
+    script_path
+        .canonicalize()
+        .map_err(|source| Error::ScriptNotFound {
+            path: script_path.to_path_buf(),
+            source,
+        })
 }
 
 /// Find the path of the `uv` command.
 ///
 /// The function returns `Ok<String>` if `uv` is found, where the string is the path of
-/// the `uv` command. If `uv` is not found, the function prints a hint to install it
-/// and returns an `Err`.
+/// the `uv` command.
 ///
 /// The function uses `which` or `where` command to find the path of `uv`. If the command
-/// is not successful, it means `uv` is not installed, so the function prints a hint to
-/// install it and returns an `Err`.
+/// is not successful, it means `uv` is not installed. Use [`uv_install_hint`] to tell the
+/// user how to install it.
 ///
 /// # Errors
 ///
-/// The function returns an `Err` if `uv` is not installed.
-pub fn get_uv_path() -> anyhow::Result<String> {
+/// The function returns [`Error::UvNotFound`] if `uv` is not installed.
+pub fn get_uv_path() -> Result<String> {
     // For Unix-like systems (Linux, macOS)
     #[cfg(not(target_os = "windows"))]
     let find_executable = "which";
@@ -107,35 +178,70 @@ pub fn get_uv_path() -> anyhow::Result<String> {
     #[cfg(target_os = "windows")]
     let find_executable = "where";
 
-    let output = Command::new(find_executable).arg("uv").output()?;
+    let output = Command::new(find_executable)
+        .arg("uv")
+        .output()
+        .map_err(|source| Error::SpawnFailed {
+            program: find_executable.to_string(),
+            source,
+        })?;
     if output.status.success() {
         // found uv
-        let path = String::from_utf8(output.stdout)?.trim().to_string();
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
         Ok(path)
     } else {
-        // not found uv, hint to install it
-
-        // for unix, run wget -qO- https://astral.sh/uv/install.sh | sh
-        eprintln!("Please run the following command to install uv:");
-        #[cfg(not(target_os = "windows"))]
-        eprintln!("wget -qO- https://astral.sh/uv/install.sh | sh");
-
-        // for windows, run powershell -ExecutionPolicy ByPass -c "irm https://astral.sh/uv/install.ps1 | iex"
-        #[cfg(target_os = "windows")]
-        eprintln!(
-            "powershell -ExecutionPolicy ByPass -c \"irm https://astral.sh/uv/install.ps1 | iex\""
-        );
-        Err(anyhow!("uv not installed"))
+        Err(Error::UvNotFound)
     }
 }
 
+/// Finds the executable `name` in the directories of `PATH`.
+///
+/// On Windows, the extensions `.exe`, `.bat` and `.cmd` are tried as well.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(target_os = "windows") {
+        &["exe", "bat", "cmd"]
+    } else {
+        &[]
+    };
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| {
+        let executable = dir.join(name);
+        if executable.is_file() {
+            return Some(executable);
+        }
+        extensions
+            .iter()
+            .map(|extension| executable.with_extension(extension))
+            .find(|executable| executable.is_file())
+    })
+}
+
+/// Returns the command to install `uv` on the current platform.
+pub fn uv_install_hint() -> &'static str {
+    // for unix, run wget -qO- https://astral.sh/uv/install.sh | sh
+    #[cfg(not(target_os = "windows"))]
+    let hint = "wget -qO- https://astral.sh/uv/install.sh | sh";
+
+    // for windows, run powershell -ExecutionPolicy ByPass -c "irm https://astral.sh/uv/install.ps1 | iex"
+    #[cfg(target_os = "windows")]
+    let hint =
+        "powershell -ExecutionPolicy ByPass -c \"irm https://astral.sh/uv/install.ps1 | iex\"";
+
+    hint
+}
+
 /// Returns the path of the Python executable within the given virtual environment.
 ///
 /// For Unix-like systems (Linux, macOS), the Python executable is located in the `bin` directory.
 ///
 /// For Windows, the Python executable is located in the `Scripts` directory, and has the `.exe` extension.
-pub fn get_python_exec_path(venv_path: &PathBuf) -> PathBuf {
+/// Conda environments on Windows have it directly in the environment directory instead.
+pub fn get_python_exec_path(venv_path: &Path) -> PathBuf {
     PathBuf::from(if cfg!(target_os = "windows") {
+        let conda_python = venv_path.join("python.exe");
+        if conda_python.exists() && !venv_path.join("Scripts").join("python.exe").exists() {
+            return conda_python;
+        }
         venv_path
             .join("Scripts")
             .join("python.exe")
@@ -0,0 +1,66 @@
+use crate::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A parsed `pyproject.toml`.
+///
+/// Only the parts used by py_executer are exposed, the full document is kept as a table.
+#[derive(Debug, Clone)]
+pub struct PyProject {
+    /// The path of the file.
+    pub path: PathBuf,
+    table: toml::Table,
+}
+
+impl PyProject {
+    /// Reads and parses the `pyproject.toml` at `path`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidPyProject`] if the file cannot be read or is not
+    /// valid TOML.
+    pub fn read(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidPyProject {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        let table = content
+            .parse::<toml::Table>()
+            .map_err(|err| invalid(err.to_string()))?;
+        Ok(PyProject {
+            path: path.to_path_buf(),
+            table,
+        })
+    }
+
+    /// Returns the whole document.
+    pub fn table(&self) -> &toml::Table {
+        &self.table
+    }
+
+    /// Returns the value at the dotted `key`, e.g. `project.requires-python`.
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        let mut parts = key.split('.');
+        let mut value = self.table.get(parts.next()?)?;
+        for part in parts {
+            value = value.as_table()?.get(part)?;
+        }
+        Some(value)
+    }
+
+    /// Returns the `[tool.py_executer]` table, the project configuration of py_executer.
+    pub fn tool_config(&self) -> Option<&toml::Table> {
+        self.get("tool.py_executer")?.as_table()
+    }
+
+    /// Returns `[project].name`.
+    pub fn project_name(&self) -> Option<&str> {
+        self.get("project.name")?.as_str()
+    }
+
+    /// Returns `[project].requires-python`.
+    pub fn requires_python(&self) -> Option<&str> {
+        self.get("project.requires-python")?.as_str()
+    }
+}
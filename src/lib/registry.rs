@@ -0,0 +1,298 @@
+use crate::lock::FileLock;
+use crate::store::cache_dir;
+use crate::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name of the registry file, under the cache directory.
+pub const REGISTRY_FILE_NAME: &str = "venvs.toml";
+
+/// The time to wait for the registry lock, it is only held for short updates.
+const REGISTRY_LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A venv created by py_executer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenvRecord {
+    /// The path of the venv.
+    pub path: PathBuf,
+    /// The project the venv was created for.
+    pub project: PathBuf,
+    /// When the venv was created.
+    pub created: SystemTime,
+    /// When a script was last run with the venv.
+    pub last_used: SystemTime,
+}
+
+impl VenvRecord {
+    /// Returns `true` if the venv was not used for longer than `age`.
+    pub fn is_older_than(&self, age: Duration) -> bool {
+        SystemTime::now()
+            .duration_since(self.last_used)
+            .is_ok_and(|elapsed| elapsed > age)
+    }
+
+    /// Returns the key of the lock held while the venv is prepared, see
+    /// [`lock_path`](crate::lock::lock_path).
+    ///
+    /// This is the project for the venv of a project, and the venv itself for the venv of a
+    /// script in the central store.
+    pub fn lock_key(&self) -> &Path {
+        if self.project.is_file() {
+            &self.path
+        } else {
+            &self.project
+        }
+    }
+}
+
+/// The record of the venvs created by py_executer, stored as TOML in the cache directory.
+///
+/// Only venvs in the registry are listed by `py_executer venvs list` and removed by
+/// `py_executer venvs gc`, venvs created by other tools are never touched.
+#[derive(Debug, Clone, Default)]
+pub struct VenvRegistry {
+    path: PathBuf,
+    records: Vec<VenvRecord>,
+}
+
+impl VenvRegistry {
+    /// Opens the registry under the cache directory, see
+    /// [`cache_dir`](crate::store::cache_dir).
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidRegistry`] if the cache directory cannot be
+    /// determined, or the registry cannot be read or parsed.
+    pub fn open() -> Result<Self> {
+        VenvRegistry::load(&default_path()?)
+    }
+
+    /// Locks the registry under the cache directory against changes by other processes.
+    ///
+    /// The lock is held until the returned value is dropped. Changes should be made with the
+    /// lock held, so concurrent runs do not lose each other's records.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidRegistry`] if the cache directory cannot be
+    /// determined, or the errors of [`FileLock::acquire`].
+    pub fn lock() -> Result<FileLock> {
+        let path = default_path()?.with_extension("toml.lock");
+        FileLock::acquire(&path, REGISTRY_LOCK_TIMEOUT, || {})
+    }
+
+    /// Opens the registry with the lock held and saves it if `update` returns `true`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns the errors of [`VenvRegistry::lock`], [`VenvRegistry::open`] and
+    /// [`VenvRegistry::save`].
+    pub fn update(update: impl FnOnce(&mut VenvRegistry) -> bool) -> Result<()> {
+        let _lock = VenvRegistry::lock()?;
+        let mut registry = VenvRegistry::open()?;
+        if update(&mut registry) {
+            registry.save()?;
+        }
+        Ok(())
+    }
+
+    /// Loads the registry from `path`, an empty registry is returned if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidRegistry`] if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidRegistry {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(invalid(err.to_string())),
+        };
+        let table = content
+            .parse::<toml::Table>()
+            .map_err(|err| invalid(err.to_string()))?;
+        let records = match table.get("venv") {
+            None => Vec::new(),
+            Some(venvs) => venvs
+                .as_array()
+                .ok_or_else(|| invalid("venv must be an array of tables".to_string()))?
+                .iter()
+                .map(|venv| parse_record(venv).ok_or_else(|| invalid("invalid venv".to_string())))
+                .collect::<Result<_>>()?,
+        };
+        Ok(VenvRegistry {
+            path: path.to_path_buf(),
+            records,
+        })
+    }
+
+    /// Returns the path of the registry file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the recorded venvs.
+    pub fn records(&self) -> &[VenvRecord] {
+        &self.records
+    }
+
+    /// Records a venv created at `venv_path` for `project`, replacing an earlier record.
+    pub fn record_created(&mut self, venv_path: &Path, project: &Path) {
+        let now = SystemTime::now();
+        self.remove(venv_path);
+        self.records.push(VenvRecord {
+            path: venv_path.to_path_buf(),
+            project: project.to_path_buf(),
+            created: now,
+            last_used: now,
+        });
+    }
+
+    /// Updates the last use of the venv at `venv_path`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the venv is recorded.
+    pub fn touch(&mut self, venv_path: &Path) -> bool {
+        match self
+            .records
+            .iter_mut()
+            .find(|record| record.path == venv_path)
+        {
+            Some(record) => {
+                record.last_used = SystemTime::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the record of the venv at `venv_path`, the venv itself is not removed.
+    pub fn remove(&mut self, venv_path: &Path) {
+        self.records.retain(|record| record.path != venv_path);
+    }
+
+    /// Writes the registry back to its file.
+    ///
+    /// The file is written to a temporary file first and renamed, so readers never see a
+    /// partially written registry.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidRegistry`] if the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let invalid = |err: std::io::Error| Error::InvalidRegistry {
+            path: self.path.clone(),
+            reason: err.to_string(),
+        };
+        let venvs: Vec<toml::Value> = self
+            .records
+            .iter()
+            .map(|record| {
+                let mut table = toml::Table::new();
+                table.insert(
+                    "path".to_string(),
+                    record.path.to_string_lossy().to_string().into(),
+                );
+                table.insert(
+                    "project".to_string(),
+                    record.project.to_string_lossy().to_string().into(),
+                );
+                table.insert("created".to_string(), unix_seconds(record.created).into());
+                table.insert(
+                    "last_used".to_string(),
+                    unix_seconds(record.last_used).into(),
+                );
+                toml::Value::Table(table)
+            })
+            .collect();
+        let mut table = toml::Table::new();
+        table.insert("venv".to_string(), toml::Value::Array(venvs));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(invalid)?;
+        }
+        let temp_path = self
+            .path
+            .with_extension(format!("toml.{}.tmp", std::process::id()));
+        fs::write(&temp_path, table.to_string()).map_err(invalid)?;
+        fs::rename(&temp_path, &self.path).map_err(invalid)
+    }
+}
+
+/// Returns the path of the registry file under the cache directory.
+fn default_path() -> Result<PathBuf> {
+    cache_dir()
+        .map(|dir| dir.join(REGISTRY_FILE_NAME))
+        .ok_or_else(|| Error::InvalidRegistry {
+            path: PathBuf::from(REGISTRY_FILE_NAME),
+            reason: "the cache directory cannot be determined".to_string(),
+        })
+}
+
+fn parse_record(venv: &toml::Value) -> Option<VenvRecord> {
+    let time = |key: &str| {
+        let seconds = venv.get(key)?.as_integer()?;
+        Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+    };
+    Some(VenvRecord {
+        path: PathBuf::from(venv.get("path")?.as_str()?),
+        project: PathBuf::from(venv.get("project")?.as_str()?),
+        created: time("created")?,
+        last_used: time("last_used")?,
+    })
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Parses a duration like `30d`, `12h`, `45m`, `90s` or `2w`.
+///
+/// # Errors
+///
+/// The function returns an `Err` describing the problem if the number or the unit is invalid,
+/// or if the duration does not fit into 64 bits of seconds.
+pub fn parse_duration(duration: &str) -> std::result::Result<Duration, String> {
+    let duration = duration.trim();
+    let unit_start = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in {}, use s, m, h, d or w", duration))?;
+    let (number, unit) = duration.split_at(unit_start);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid number in {}", duration))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid unit {}, use s, m, h, d or w", unit)),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("{} is too long", duration))
+}
+
+/// Returns the total size of the files under `path`, in bytes.
+///
+/// Symbolic links are not followed, e.g. the interpreter links of a venv.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| dir_size(&entry.path())).sum())
+        .unwrap_or_default()
+}
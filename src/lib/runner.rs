@@ -0,0 +1,1126 @@
+use crate::activation::Activation;
+use crate::conda::{CondaTool, EnvironmentFile};
+use crate::deps::{Dependencies, DependencySelection, SyncMode, SyncTarget};
+use crate::fingerprint::{read_fingerprint, write_fingerprint};
+use crate::lock::{DEFAULT_LOCK_TIMEOUT, FileLock, lock_path, venv_use_lock_path};
+use crate::path::{
+    VenvOptions, VenvSource, find_explicit_venv, get_python_native_path, get_venv_path,
+    prepare_store_venv,
+};
+use crate::process::{DEFAULT_GRACE_PERIOD, ForwardingChild, SignalGuard, exit_code, exit_signal};
+use crate::project::find_project_root;
+use crate::registry::VenvRegistry;
+use crate::script::ScriptMetadata;
+use crate::store::store_venv_path;
+use crate::venv::{VenvHealth, check_venv};
+use crate::version::{find_project_python_request, parse_request};
+use crate::workspace::{Workspace, WorkspaceMember};
+use crate::{
+    Error, Result, append_to_pythonpath, get_python_exec_path, get_uv_path, pythonpath_roots,
+    set_additional_env_var, uv_install_hint, validate_to_absolute_path, warning_println,
+};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Duration;
+
+/// Description of a single script run.
+///
+/// A `RunSpec` is created with [`RunSpec::new`] and configured with the chained setters,
+/// then handed to a [`Runner`] to be executed.
+///
+/// # Example
+///
+/// ```no_run
+/// use py_executer_lib::runner::{RunSpec, Runner};
+///
+/// let spec = RunSpec::new("main.py")
+///     .project("/path/to/project")
+///     .env("DEBUG=true")
+///     .quiet(true)
+///     .arg("--input")
+///     .arg("data.txt");
+/// let outcome = Runner::new(spec).run()?;
+/// assert!(outcome.success());
+/// # Ok::<(), py_executer_lib::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct RunSpec {
+    script: PathBuf,
+    project: Option<PathBuf>,
+    env: Vec<String>,
+    env_files: Vec<PathBuf>,
+    pythonpath: Vec<PathBuf>,
+    no_pythonpath: bool,
+    requirements: Vec<PathBuf>,
+    constraints: Vec<PathBuf>,
+    selection: DependencySelection,
+    mode: SyncMode,
+    package: Option<String>,
+    python: Option<String>,
+    venv: Option<PathBuf>,
+    rebuild_venv: bool,
+    force_sync: bool,
+    central_venv: bool,
+    lock_timeout: Duration,
+    quiet: bool,
+    clean: bool,
+    forward_signals: bool,
+    grace_period: Duration,
+    args: Vec<String>,
+}
+
+impl RunSpec {
+    /// Creates a new spec for the given script.
+    ///
+    /// Unless [`RunSpec::project`] is set, the project root is detected from the location of the
+    /// script, see [`find_project_root`].
+    pub fn new(script: impl Into<PathBuf>) -> Self {
+        RunSpec {
+            script: script.into(),
+            project: None,
+            env: Vec::new(),
+            env_files: Vec::new(),
+            pythonpath: Vec::new(),
+            no_pythonpath: false,
+            requirements: Vec::new(),
+            constraints: Vec::new(),
+            selection: DependencySelection::default(),
+            mode: SyncMode::default(),
+            package: None,
+            python: None,
+            venv: None,
+            rebuild_venv: false,
+            force_sync: false,
+            central_venv: false,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            quiet: false,
+            clean: false,
+            forward_signals: false,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            args: Vec::new(),
+        }
+    }
+
+    /// Sets the project path, where the venv and dependency files are looked up.
+    pub fn project(mut self, project: impl Into<PathBuf>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// Adds an environment variable in the format `KEY=VALUE`.
+    pub fn env(mut self, env: impl Into<String>) -> Self {
+        self.env.push(env.into());
+        self
+    }
+
+    /// Adds several environment variables in the format `KEY=VALUE`.
+    pub fn envs<I, S>(mut self, envs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.env.extend(envs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a .env file whose variables are passed to the script.
+    ///
+    /// If no env file is added, the `.env` under the project path is used if it exists. A later
+    /// file overrides an earlier one, the variables of the current process take precedence. The
+    /// environment of the current process is not changed.
+    pub fn env_file(mut self, env_file: impl Into<PathBuf>) -> Self {
+        self.env_files.push(env_file.into());
+        self
+    }
+
+    /// Adds an entry to append to `PYTHONPATH`, after the project and its `src` directory.
+    ///
+    /// Relative paths are resolved against the current directory.
+    pub fn pythonpath(mut self, pythonpath: impl Into<PathBuf>) -> Self {
+        self.pythonpath.push(pythonpath.into());
+        self
+    }
+
+    /// Leaves `PYTHONPATH` as is, instead of appending the project and its `src` directory.
+    pub fn no_pythonpath(mut self, no_pythonpath: bool) -> Self {
+        self.no_pythonpath = no_pythonpath;
+        self
+    }
+
+    /// Adds a requirements file to install, can be called multiple times.
+    ///
+    /// If no requirements file is added, the `requirements.txt` of the project is installed if
+    /// it exists. Relative paths are resolved against the current directory.
+    pub fn requirements(mut self, requirements: impl Into<PathBuf>) -> Self {
+        self.requirements.push(requirements.into());
+        self
+    }
+
+    /// Adds a constraints file, applied to every `pip install`, can be called multiple times.
+    ///
+    /// Relative paths are resolved against the current directory.
+    pub fn constraints(mut self, constraints: impl Into<PathBuf>) -> Self {
+        self.constraints.push(constraints.into());
+        self
+    }
+
+    /// Adds an optional dependency of the project to install, `uv sync --extra`.
+    ///
+    /// The extras and dependency groups not set on the spec are read from the
+    /// `[tool.py_executer]` table of the `pyproject.toml`, see [`DependencySelection`].
+    pub fn extra(mut self, extra: impl Into<String>) -> Self {
+        self.selection.extras.push(extra.into());
+        self
+    }
+
+    /// Installs all optional dependencies of the project, `uv sync --all-extras`.
+    pub fn all_extras(mut self, all_extras: bool) -> Self {
+        self.selection.all_extras = all_extras;
+        self
+    }
+
+    /// Adds a dependency group of the project to install, `uv sync --group`.
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.selection.groups.push(group.into());
+        self
+    }
+
+    /// Does not install the `dev` dependency group, `uv sync --no-dev`.
+    pub fn no_dev(mut self, no_dev: bool) -> Self {
+        self.selection.no_dev = no_dev;
+        self
+    }
+
+    /// Installs only the given dependency group, without the project, `uv sync --only-group`.
+    pub fn only_group(mut self, group: impl Into<String>) -> Self {
+        self.selection.only_groups.push(group.into());
+        self
+    }
+
+    /// Fails if `uv.lock` is missing or out of date with `pyproject.toml`, `uv sync --locked`.
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.mode.locked = locked;
+        self
+    }
+
+    /// Installs `uv.lock` as is, without checking it against `pyproject.toml`, `uv sync --frozen`.
+    pub fn frozen(mut self, frozen: bool) -> Self {
+        self.mode.frozen = frozen;
+        self
+    }
+
+    /// Installs the dependencies without network access.
+    ///
+    /// uv only uses its cache and the [`RunSpec::find_links`], the native pip is run with
+    /// `--no-index`, see [`SyncMode`].
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.mode.offline = offline;
+        self
+    }
+
+    /// Adds a local directory or URL to look for packages in, `--find-links`.
+    pub fn find_links(mut self, find_links: impl Into<String>) -> Self {
+        self.mode.find_links.push(find_links.into());
+        self
+    }
+
+    /// Syncs and runs the given member of the uv workspace of the project, `uv sync --package`.
+    ///
+    /// Without it, the member whose directory contains the script is used, see
+    /// [`Workspace::member_of`].
+    pub fn package(mut self, package: impl Into<String>) -> Self {
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Requests a Python interpreter, e.g. `3.11`, `>=3.10,<3.13`, `pypy3.10` or a path.
+    ///
+    /// If no request is given, the `.python-version` file and the `requires-python` of the
+    /// `pyproject.toml` of the project are used. A script with inline metadata uses its own
+    /// `requires-python` instead, see [`ScriptMetadata`].
+    pub fn python(mut self, python: impl Into<String>) -> Self {
+        self.python = Some(python.into());
+        self
+    }
+
+    /// Uses the given environment instead of looking for one.
+    ///
+    /// Without it, an activated environment from `VIRTUAL_ENV` or `CONDA_PREFIX` is used, then
+    /// a `venv` or `.venv` under the project, see [`find_explicit_venv`].
+    pub fn venv(mut self, venv: impl Into<PathBuf>) -> Self {
+        self.venv = Some(venv.into());
+        self
+    }
+
+    /// Recreates the project venv if it is broken, instead of returning an error.
+    ///
+    /// A venv is broken if its base interpreter was removed, if it was moved from another
+    /// location, or if its Python does not match the request. Environments selected with
+    /// [`RunSpec::venv`] or by activation are never recreated.
+    pub fn rebuild_venv(mut self, rebuild_venv: bool) -> Self {
+        self.rebuild_venv = rebuild_venv;
+        self
+    }
+
+    /// Syncs the dependencies even if they did not change since the last sync.
+    ///
+    /// By default, the dependencies are only synced if the fingerprint of the dependency files
+    /// and the interpreter differs from the one stored in the venv at the last successful sync.
+    pub fn force_sync(mut self, force_sync: bool) -> Self {
+        self.force_sync = force_sync;
+        self
+    }
+
+    /// Creates the venv in the central store under the cache directory instead of the project.
+    ///
+    /// The venvs in the store are keyed by the project path and a hash of its dependencies, and
+    /// reused across runs. An existing `venv` or `.venv` in the project is still used. The
+    /// store is also used if the project directory is not writable.
+    pub fn central_venv(mut self, central_venv: bool) -> Self {
+        self.central_venv = central_venv;
+        self
+    }
+
+    /// Sets how long to wait for another process preparing the same venv, 5 minutes by default.
+    ///
+    /// Creating the venv and syncing its dependencies is guarded by a lock per project, or per
+    /// venv for an explicitly selected one, so concurrent runs do not corrupt the venv.
+    pub fn lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+        self
+    }
+
+    /// Suppresses the informational output of the runner.
+    ///
+    /// The stdout and stderr of the script are not affected.
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Removes the venv and config files created for this run after execution.
+    ///
+    /// Files that existed before the run are never deleted.
+    pub fn clean(mut self, clean: bool) -> Self {
+        self.clean = clean;
+        self
+    }
+
+    /// Forwards `SIGINT`, `SIGTERM` and `SIGHUP` sent to the current process to the script.
+    ///
+    /// The signal handlers of the process are replaced for the whole run, see [`SignalGuard`],
+    /// so this is meant for command line tools running one script at a time. A signal received
+    /// before the script started stops the run with [`Error::Interrupted`]. Defaults to `false`.
+    ///
+    /// Signals are not forwarded to the processes preparing the venv, e.g. `uv` or `pip`. A
+    /// Ctrl-C in a terminal reaches them as well, but a signal sent only to the current process
+    /// lets them finish and stops the run before the script is started.
+    pub fn forward_signals(mut self, forward_signals: bool) -> Self {
+        self.forward_signals = forward_signals;
+        self
+    }
+
+    /// Sets the time the script is given to exit after a forwarded signal, before it is killed.
+    ///
+    /// Only used with [`RunSpec::forward_signals`]. Defaults to [`DEFAULT_GRACE_PERIOD`].
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Adds an argument to pass to the script.
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds several arguments to pass to the script.
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+}
+
+/// The result of a finished run.
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    /// The exit status of the script process.
+    pub status: ExitStatus,
+    /// The venv the script was executed in, `None` if uv managed the environment of a script
+    /// with inline metadata.
+    pub venv: Option<PathBuf>,
+}
+
+impl RunOutcome {
+    /// Returns `true` if the script exited successfully.
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    /// Returns the exact exit code of the script.
+    ///
+    /// If the script was killed by a signal, `128 + signum` is returned, like shells do.
+    pub fn code(&self) -> i32 {
+        exit_code(&self.status)
+    }
+
+    /// Returns the signal that killed the script, if any.
+    pub fn signal(&self) -> Option<i32> {
+        exit_signal(&self.status)
+    }
+}
+
+/// Executes a [`RunSpec`].
+///
+/// The runner resolves uv or the native Python, finds or creates the venv, prepares the
+/// dependencies, loads the environment and finally runs the script. It never exits the
+/// current process, all failures are returned as errors.
+#[derive(Debug, Clone)]
+pub struct Runner {
+    spec: RunSpec,
+}
+
+impl Runner {
+    /// Creates a runner for the given spec.
+    pub fn new(spec: RunSpec) -> Self {
+        Runner { spec }
+    }
+
+    /// Returns the spec this runner executes.
+    pub fn spec(&self) -> &RunSpec {
+        &self.spec
+    }
+
+    /// Runs the script and waits for it to finish.
+    ///
+    /// With [`RunSpec::forward_signals`], `SIGINT`, `SIGTERM` and `SIGHUP` received while the
+    /// script runs are forwarded to the process group of the script, which is killed if it does
+    /// not exit within the grace period.
+    ///
+    /// If clean mode is enabled, the files created during the run are removed afterwards,
+    /// whether the run succeeded or not, unless another run is still using the venv.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an [`Error`] if the script or project cannot be found, if no Python
+    /// is available, if the venv or the dependencies cannot be prepared or if the script cannot
+    /// be spawned. A script exiting with a non-zero status is not an error, see
+    /// [`RunOutcome::status`].
+    pub fn run(&self) -> Result<RunOutcome> {
+        // caught from the start, so a signal during the preparation does not skip the cleanup
+        let signals = self.spec.forward_signals.then(SignalGuard::install);
+        let mut cleanup = Cleanup::default();
+        let outcome = match self.execute(&mut cleanup, signals.as_ref()) {
+            // e.g. the installer was interrupted by the same Ctrl-C
+            Err(err) => match signals.as_ref().and_then(SignalGuard::received) {
+                Some(signal) => Err(Error::Interrupted { signal }),
+                None => Err(err),
+            },
+            outcome => outcome,
+        };
+        if self.spec.clean {
+            cleanup.run(self.spec.lock_timeout, self.spec.quiet);
+        }
+        outcome
+    }
+
+    fn execute(&self, cleanup: &mut Cleanup, signals: Option<&SignalGuard>) -> Result<RunOutcome> {
+        let spec = &self.spec;
+        let quiet = spec.quiet;
+
+        if !quiet {
+            println!("------------------");
+        }
+
+        // Get the absolute path of the script and the current runtime directory
+        let script_path = validate_to_absolute_path(&spec.script)?;
+        let runtime_path = match &spec.project {
+            Some(project) => project
+                .canonicalize()
+                .map_err(|source| Error::ProjectNotFound {
+                    path: project.clone(),
+                    source,
+                })?,
+            None => detect_project_root(&script_path, quiet),
+        };
+
+        // Get uv installation information
+        let uv_path = get_uv_path().unwrap_or_default();
+        if !uv_path.is_empty() {
+            // uv is installed
+            if !quiet {
+                println!("Using uv from: {}", uv_path.bold());
+            }
+        } else {
+            // uv is not installed, will try native python
+            if !quiet {
+                warning_println!("Failed to get uv path, will not use it then");
+                println!("To install uv, run: {}", uv_install_hint());
+            }
+        }
+
+        // A script with inline metadata runs in its own environment, never in the project venv,
+        // so the project and its workspace are not even read
+        if let Some(metadata) = ScriptMetadata::read(&script_path)? {
+            return self.execute_script(&metadata, &script_path, &runtime_path, &uv_path, signals);
+        }
+
+        // In a uv workspace, the workspace root is the project, and the member owning the
+        // script is synced and run
+        let workspace = if uv_path.is_empty() {
+            if spec.package.is_some() && !quiet {
+                warning_println!("Workspace members need uv, will not use --package");
+            }
+            None
+        } else {
+            Workspace::find(&runtime_path)?
+        };
+        let member = if uv_path.is_empty() {
+            None
+        } else {
+            select_member(
+                workspace.as_ref(),
+                spec.package.as_deref(),
+                &script_path,
+                &runtime_path,
+                quiet,
+            )?
+        };
+        let runtime_path = workspace
+            .as_ref()
+            .map_or(runtime_path, |workspace| workspace.root.clone());
+
+        let dependencies = Dependencies::resolve(
+            &runtime_path,
+            &spec.requirements,
+            &spec.constraints,
+            spec.selection.clone(),
+        )?;
+
+        // Get the requested python, from args or the project
+        let python_request = match &spec.python {
+            Some(python) => Some(parse_request(python)?),
+            None => find_project_python_request(&runtime_path, quiet)?.map(|(request, source)| {
+                if !quiet {
+                    println!(
+                        "Using python request {} from {}",
+                        request.to_string().bold(),
+                        source.display()
+                    );
+                }
+                request
+            }),
+        };
+
+        // Validate provided or activated venv
+        let mut explicit_venv = find_explicit_venv(spec.venv.as_deref(), quiet)?;
+
+        // Otherwise the conda environment of an environment.yml is used instead of a venv
+        let environment_file = match explicit_venv {
+            Some(_) => None,
+            None => EnvironmentFile::find(&runtime_path)
+                .map(|path| EnvironmentFile::read(&path))
+                .transpose()?,
+        };
+
+        // Lock the venv against concurrent runs until it is created and synced
+        let lock_key = explicit_venv
+            .as_ref()
+            .map_or(runtime_path.as_path(), |(venv, _)| venv.as_path());
+        let venv_lock = FileLock::acquire(&lock_path(lock_key), spec.lock_timeout, || {
+            if !quiet {
+                warning_println!(
+                    "Waiting for another py_executer process to finish preparing the venv of {} (up to {}s)",
+                    lock_key.display(),
+                    spec.lock_timeout.as_secs()
+                );
+            }
+        })?;
+        cleanup.lock_key = Some(lock_key.to_path_buf());
+
+        if let Some(environment_file) = &environment_file {
+            match CondaTool::find() {
+                Some(tool) => {
+                    if !quiet {
+                        println!(
+                            "Using conda environment from {} with {}",
+                            environment_file.path.display(),
+                            tool.path.display().to_string().bold()
+                        );
+                    }
+                    let prefix = tool.prepare(environment_file, spec.mode.offline, quiet)?;
+                    explicit_venv = Some((prefix, VenvSource::EnvironmentFile));
+                }
+                None => {
+                    if !quiet {
+                        warning_println!(
+                            "Found {} but no conda, mamba or micromamba, will use a venv instead",
+                            environment_file.path.display()
+                        );
+                    }
+                }
+            }
+        }
+
+        let venv = match &explicit_venv {
+            Some((venv, source)) => {
+                if !quiet {
+                    println!("Using venv from {}", source.to_string().bold());
+                }
+                if let VenvHealth::Broken(reason) = check_venv(venv, python_request.as_ref(), false)
+                {
+                    return Err(Error::BrokenVenv {
+                        path: venv.clone(),
+                        reason,
+                    });
+                }
+                venv.clone()
+            }
+            None => {
+                // Get python native as backup
+                let python_native_path = get_python_native_path(&uv_path, python_request.as_ref());
+
+                // If uv and native python are both empty, exit with error
+                if python_native_path.is_empty() && uv_path.is_empty() {
+                    return Err(match python_request {
+                        Some(python_request) => Error::NoMatchingPython {
+                            request: python_request.to_string(),
+                        },
+                        None => Error::NoPythonFound,
+                    });
+                }
+                if !python_native_path.is_empty() && !quiet {
+                    println!("Using python from: {}", python_native_path.bold());
+                }
+
+                // try to find a possible venv under current directory
+                // or create a new venv
+                get_venv_path(
+                    &runtime_path,
+                    &VenvOptions {
+                        uv_path: &uv_path,
+                        python_native_path: &python_native_path,
+                        python_request: python_request.as_ref(),
+                        quiet,
+                        clean: spec.clean,
+                        rebuild: spec.rebuild_venv,
+                        central: spec.central_venv,
+                        offline: spec.mode.offline,
+                        dependency_hash: &dependencies.hash(&runtime_path, python_request.as_ref()),
+                    },
+                    &mut cleanup.files,
+                )?
+            }
+        };
+        let explicit_source = explicit_venv.map(|(_, source)| source);
+        touch_venv(&venv, quiet);
+
+        let python_exec_path = get_python_exec_path(&venv);
+
+        // Only sync the dependencies if their inputs changed since the last sync
+        let target = SyncTarget {
+            runtime_path: &runtime_path,
+            uv_path: &uv_path,
+            venv: &venv,
+            explicit_source,
+            python_exec_path: &python_exec_path,
+            mode: &spec.mode,
+            workspace: workspace.as_ref(),
+            member,
+        };
+        sync_dependencies(&dependencies, &target, spec.force_sync, quiet)?;
+
+        // Mark the venv as in use until the script exited, so other runs do not clean it up
+        let _venv_use =
+            FileLock::acquire_shared(&venv_use_lock_path(&venv), spec.lock_timeout, || {})?;
+        cleanup.venv = Some(venv.clone());
+        drop(venv_lock);
+
+        if !quiet {
+            println!("Using venv: {}", venv.display().to_string().bold());
+        }
+
+        // Construct the command
+        // an explicit environment is already prepared and does not belong to the project,
+        // so the script is run with its interpreter directly, like the projects of other
+        // package managers
+        let mut py_cmd =
+            if !uv_path.is_empty() && explicit_source.is_none() && dependencies.manager.is_none() {
+                let mut cmd = Command::new(&uv_path);
+                // the dependencies are already synced
+                cmd.arg("run")
+                    .arg("--no-sync")
+                    .arg("--project")
+                    .arg(&runtime_path)
+                    .env("UV_PROJECT_ENVIRONMENT", &venv);
+                if let Some(member) = member {
+                    cmd.arg("--package").arg(&member.name);
+                }
+                cmd
+            } else {
+                Command::new(&python_exec_path)
+            };
+        // the script and the tools it shells out to run as in the activated venv
+        Activation::new(&venv).apply(&mut py_cmd);
+        let status = self.run_script(py_cmd, &script_path, &runtime_path, signals)?;
+
+        Ok(RunOutcome {
+            status,
+            venv: Some(venv),
+        })
+    }
+
+    /// Runs a script with inline metadata in its own environment.
+    ///
+    /// With uv, the script is run with `uv run --script`, which creates and caches the
+    /// environment itself. Otherwise a venv in the central store, keyed by the script path and
+    /// its metadata, is created and the dependencies of the script are installed into it.
+    fn execute_script(
+        &self,
+        metadata: &ScriptMetadata,
+        script_path: &Path,
+        runtime_path: &Path,
+        uv_path: &str,
+        signals: Option<&SignalGuard>,
+    ) -> Result<RunOutcome> {
+        let spec = &self.spec;
+        let quiet = spec.quiet;
+        if !quiet {
+            println!(
+                "Using the inline script metadata of {}, will run the script in its own venv",
+                script_path.display()
+            );
+            if spec.venv.is_some() {
+                warning_println!(
+                    "Scripts with inline metadata run in their own venv, will not use --venv"
+                );
+            }
+            if spec.package.is_some() {
+                warning_println!(
+                    "Scripts with inline metadata run in their own venv, will not use --package"
+                );
+            }
+            if !spec.requirements.is_empty() || !spec.constraints.is_empty() {
+                warning_println!(
+                    "Requirements and constraints files do not apply to scripts with inline metadata, will not install them"
+                );
+            }
+        }
+
+        if !uv_path.is_empty() {
+            let mut cmd = Command::new(uv_path);
+            cmd.arg("run").arg("--script");
+            if let Some(python) = &spec.python {
+                cmd.arg("--python").arg(python);
+            }
+            if quiet {
+                cmd.arg("--quiet");
+            }
+            cmd.args(spec.mode.sync_args());
+            let status = self.run_script(cmd, script_path, runtime_path, signals)?;
+            return Ok(RunOutcome { status, venv: None });
+        }
+
+        let python_request = match (&spec.python, &metadata.requires_python) {
+            (Some(python), _) => Some(parse_request(python)?),
+            (None, Some(requires_python)) => Some(parse_request(requires_python)?),
+            (None, None) => None,
+        };
+        let python_native_path = get_python_native_path(uv_path, python_request.as_ref());
+        if python_native_path.is_empty() {
+            return Err(match python_request {
+                Some(python_request) => Error::NoMatchingPython {
+                    request: python_request.to_string(),
+                },
+                None => Error::NoPythonFound,
+            });
+        }
+        if !quiet {
+            println!("Using python from: {}", python_native_path.bold());
+        }
+
+        let dependency_hash = metadata.hash(python_request.as_ref());
+        let venv = store_venv_path(script_path, &dependency_hash).ok_or_else(|| {
+            Error::VenvCreationFailed {
+                path: script_path.to_path_buf(),
+                stderr: "the central venv store cannot be determined".to_string(),
+            }
+        })?;
+        let venv_lock = FileLock::acquire(&lock_path(&venv), spec.lock_timeout, || {
+            if !quiet {
+                warning_println!(
+                    "Waiting for another py_executer process to finish preparing the venv of {} (up to {}s)",
+                    script_path.display(),
+                    spec.lock_timeout.as_secs()
+                );
+            }
+        })?;
+        let venv = prepare_store_venv(
+            venv,
+            script_path,
+            &VenvOptions {
+                uv_path,
+                python_native_path: &python_native_path,
+                python_request: python_request.as_ref(),
+                quiet,
+                clean: false,
+                rebuild: spec.rebuild_venv,
+                central: true,
+                offline: spec.mode.offline,
+                dependency_hash: &dependency_hash,
+            },
+        )?;
+        touch_venv(&venv, quiet);
+
+        let python_exec_path = get_python_exec_path(&venv);
+        let target = SyncTarget {
+            // the venv of a script belongs to no project
+            runtime_path: &venv,
+            uv_path,
+            venv: &venv,
+            explicit_source: None,
+            python_exec_path: &python_exec_path,
+            mode: &spec.mode,
+            workspace: None,
+            member: None,
+        };
+        sync_dependencies(
+            &Dependencies::for_script(metadata),
+            &target,
+            spec.force_sync,
+            quiet,
+        )?;
+        drop(venv_lock);
+
+        if !quiet {
+            println!("Using venv: {}", venv.display().to_string().bold());
+        }
+        let mut py_cmd = Command::new(&python_exec_path);
+        Activation::new(&venv).apply(&mut py_cmd);
+        let status = self.run_script(py_cmd, script_path, runtime_path, signals)?;
+
+        Ok(RunOutcome {
+            status,
+            venv: Some(venv),
+        })
+    }
+
+    /// Runs the script with `py_cmd`, an interpreter or `uv run`, and waits for it to finish.
+    ///
+    /// The variables of the env files, the PYTHONPATH roots of the project and the additional env
+    /// of the spec are passed to the script, the additional env takes precedence over the env
+    /// files and the venv activation. The environment of the current process is not changed.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::Interrupted`] if a forwarded signal was received before,
+    /// or [`Error::SpawnFailed`] if the script cannot be spawned or waited for.
+    fn run_script(
+        &self,
+        mut py_cmd: Command,
+        script_path: &Path,
+        runtime_path: &Path,
+        signals: Option<&SignalGuard>,
+    ) -> Result<ExitStatus> {
+        let spec = &self.spec;
+        let quiet = spec.quiet;
+
+        // load dot env
+        let env_file_vars = read_env_files(&spec.env_files, runtime_path, quiet);
+
+        // load additional env from args
+        let roots = if spec.no_pythonpath {
+            Vec::new()
+        } else {
+            pythonpath_roots(runtime_path, &spec.pythonpath)
+        };
+        let mut additional_env = set_additional_env_var(spec.env.clone(), &roots, quiet);
+        // a PYTHONPATH of the env files is extended like the one of the current process
+        if let Some(pythonpath) = env_file_vars.get("PYTHONPATH")
+            && !roots.is_empty()
+            && !spec.env.iter().any(|env| env.starts_with("PYTHONPATH="))
+            && let Ok(pythonpath) = append_to_pythonpath(Some(OsStr::new(pythonpath)), &roots)
+        {
+            additional_env.insert(
+                "PYTHONPATH".to_string(),
+                pythonpath.to_string_lossy().to_string(),
+            );
+        }
+
+        if !quiet {
+            println!("------------------");
+        }
+
+        py_cmd
+            .arg(script_path)
+            .args(&spec.args)
+            .envs(env_file_vars)
+            .envs(additional_env)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit());
+        let spawn_failed = |source| Error::SpawnFailed {
+            program: script_path.display().to_string(),
+            source,
+        };
+        if let Some(signal) = signals.and_then(SignalGuard::received) {
+            return Err(Error::Interrupted { signal });
+        }
+        if signals.is_some() {
+            ForwardingChild::spawn(&mut py_cmd)
+                .map_err(spawn_failed)?
+                .with_grace_period(spec.grace_period)
+                .wait()
+        } else {
+            py_cmd.spawn().and_then(|mut child| child.wait())
+        }
+        .map_err(spawn_failed)
+    }
+}
+
+/// Installs the dependencies into the venv of `target`, unless their fingerprint did not
+/// change since the last sync.
+fn sync_dependencies(
+    dependencies: &Dependencies,
+    target: &SyncTarget,
+    force: bool,
+    quiet: bool,
+) -> Result<()> {
+    let fingerprint = dependencies.fingerprint(target);
+    if !force && read_fingerprint(target.venv).as_ref() == Some(&fingerprint) {
+        if !quiet {
+            println!("Dependencies are up to date, skipping the sync");
+        }
+        return Ok(());
+    }
+    dependencies.sync(target, quiet)?;
+    if let Err(err) = write_fingerprint(target.venv, &fingerprint)
+        && !quiet
+    {
+        warning_println!("Failed to store the dependency fingerprint: {}", err);
+    }
+    Ok(())
+}
+
+/// Selects the member of the uv workspace to sync and run.
+///
+/// The member given as `package` is used, otherwise the member containing the script, then the
+/// one containing the project. A workspace with a virtual root and no matching member is synced
+/// as a whole.
+///
+/// # Errors
+///
+/// The function returns [`Error::InvalidWorkspace`] if `package` is given but the project is
+/// not part of a workspace or `package` is not one of its members.
+fn select_member<'a>(
+    workspace: Option<&'a Workspace>,
+    package: Option<&str>,
+    script_path: &Path,
+    project_path: &Path,
+    quiet: bool,
+) -> Result<Option<&'a WorkspaceMember>> {
+    let Some(workspace) = workspace else {
+        return match package {
+            Some(package) => Err(Error::InvalidWorkspace {
+                path: project_path.to_path_buf(),
+                reason: format!(
+                    "the project is not part of a uv workspace, cannot use package {}",
+                    package
+                ),
+            }),
+            None => Ok(None),
+        };
+    };
+    let member = match package {
+        Some(package) => {
+            Some(
+                workspace
+                    .member(package)
+                    .ok_or_else(|| Error::InvalidWorkspace {
+                        path: workspace.root.join("pyproject.toml"),
+                        reason: format!("{} is not a member of the workspace", package),
+                    })?,
+            )
+        }
+        None => workspace
+            .member_of(script_path)
+            .or_else(|| workspace.member_of(project_path)),
+    };
+    if !quiet {
+        match member {
+            Some(member) => println!(
+                "Using uv workspace {}, member {}",
+                workspace.root.display().to_string().bold(),
+                member.name.bold()
+            ),
+            None => println!(
+                "Using uv workspace {}, all members",
+                workspace.root.display().to_string().bold()
+            ),
+        }
+    }
+    Ok(member)
+}
+
+/// Detects the project root of the script, see [`find_project_root`].
+///
+/// If no project root is found, the directory of the script is used.
+fn detect_project_root(script_path: &Path, quiet: bool) -> PathBuf {
+    let script_dir = script_path.parent().unwrap_or(script_path);
+    match find_project_root(script_dir) {
+        Some((root, marker)) => {
+            if !quiet {
+                println!(
+                    "Using project root {} (found {})",
+                    root.display().to_string().bold(),
+                    marker
+                );
+            }
+            root
+        }
+        None => {
+            if !quiet {
+                println!(
+                    "No project root found, using the script directory {}",
+                    script_dir.display().to_string().bold()
+                );
+            }
+            script_dir.to_path_buf()
+        }
+    }
+}
+
+/// Updates the last use of the venv in the [`VenvRegistry`], if it was created by py_executer.
+fn touch_venv(venv: &Path, quiet: bool) {
+    if let Err(err) = VenvRegistry::update(|registry| registry.touch(venv))
+        && !quiet
+    {
+        warning_println!("Failed to record the use of the venv: {}", err);
+    }
+}
+
+/// Reads the variables of the given .env files, to be passed to the script.
+///
+/// If no file is given, the `.env` under the runtime path is read if it exists. A later file
+/// overrides the variables of an earlier one, variables already set in the current environment
+/// are not overridden. The current environment itself is never changed.
+fn read_env_files(
+    env_files: &[PathBuf],
+    runtime_path: &Path,
+    quiet: bool,
+) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    let mut read = |env_file: &Path| {
+        // the suggested replacement loads the file into the environment of the current process
+        #[allow(deprecated)]
+        let Ok(iter) = dotenv::from_path_iter(env_file) else {
+            return;
+        };
+        for item in iter {
+            match item {
+                // variables set in the current process take precedence, like with dotenv
+                Ok((key, value)) => {
+                    if env::var_os(&key).is_none() {
+                        vars.insert(key, value);
+                    }
+                }
+                Err(err) => {
+                    if !quiet {
+                        warning_println!(
+                            "Failed to parse env file {}, will ignore the rest of it: {}",
+                            env_file.display(),
+                            err
+                        );
+                    }
+                    return;
+                }
+            }
+        }
+    };
+    if env_files.is_empty() {
+        // if dot env not provided, but runtime path provided
+        let env_file = runtime_path.join(".env");
+        if env_file.exists() {
+            read(&env_file);
+        }
+        return vars;
+    }
+    for env_file in env_files {
+        if env_file.exists() {
+            read(env_file);
+        } else if !quiet {
+            // provided dot env does not exist
+            warning_println!(
+                "Provided env file {} does not exist, will not use it",
+                env_file.display()
+            );
+        }
+    }
+    vars
+}
+
+/// The files created by a run, removed afterwards in clean mode.
+#[derive(Debug, Default)]
+struct Cleanup {
+    /// The key of the lock guarding the preparation of the venv, see [`lock_path`].
+    lock_key: Option<PathBuf>,
+    /// The venv used by the run.
+    venv: Option<PathBuf>,
+    /// The created files and directories.
+    files: Vec<PathBuf>,
+}
+
+impl Cleanup {
+    /// Removes the created files and directories, ignoring failures.
+    ///
+    /// The venv lock is held while removing them, so no other run starts using the venv, and
+    /// nothing is removed if another run is still using it.
+    fn run(&self, lock_timeout: Duration, quiet: bool) {
+        if self.files.is_empty() {
+            return;
+        }
+        let _lock = match &self.lock_key {
+            Some(key) => match FileLock::acquire(&lock_path(key), lock_timeout, || {}) {
+                Ok(lock) => Some(lock),
+                Err(err) => {
+                    if !quiet {
+                        warning_println!("Skipping the cleanup: {}", err);
+                    }
+                    return;
+                }
+            },
+            None => None,
+        };
+        let _venv_use = match &self.venv {
+            Some(venv) => {
+                match FileLock::acquire(&venv_use_lock_path(venv), Duration::ZERO, || {}) {
+                    Ok(lock) => Some(lock),
+                    Err(_) => {
+                        if !quiet {
+                            warning_println!(
+                                "Skipping the cleanup, the venv {} is used by another py_executer process",
+                                venv.display()
+                            );
+                        }
+                        return;
+                    }
+                }
+            }
+            None => None,
+        };
+        for path in &self.files {
+            if path.is_dir() {
+                let _ = std::fs::remove_dir_all(path);
+            } else {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
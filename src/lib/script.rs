@@ -0,0 +1,148 @@
+use crate::fingerprint::Fingerprint;
+use crate::version::PythonRequest;
+use crate::{Error, Result};
+use std::fs;
+use std::path::Path;
+
+/// The inline script metadata of a script, following PEP 723.
+///
+/// The metadata is a TOML document in a comment block of the script:
+///
+/// ```python
+/// # /// script
+/// # requires-python = ">=3.11"
+/// # dependencies = [
+/// #   "requests<3",
+/// # ]
+/// # ///
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptMetadata {
+    /// The `requires-python` version specifiers.
+    pub requires_python: Option<String>,
+    /// The dependencies as PEP 508 requirement strings.
+    pub dependencies: Vec<String>,
+}
+
+impl ScriptMetadata {
+    /// Reads the metadata of the script at `path`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidScriptMetadata`] if the script cannot be read or
+    /// its metadata block is invalid.
+    ///
+    /// # Returns
+    ///
+    /// The metadata, or `None` if the script has no `script` metadata block.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let invalid = |reason: String| Error::InvalidScriptMetadata {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let content = fs::read_to_string(path).map_err(|err| invalid(err.to_string()))?;
+        ScriptMetadata::parse(&content).map_err(invalid)
+    }
+
+    /// Parses the metadata from the content of a script.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an `Err` describing the problem if the block is not closed, appears
+    /// more than once or is not valid TOML.
+    pub fn parse(content: &str) -> std::result::Result<Option<Self>, String> {
+        let Some(toml) = find_block(content, "script")? else {
+            return Ok(None);
+        };
+        let table = toml.parse::<toml::Table>().map_err(|err| err.to_string())?;
+
+        let requires_python = match table.get("requires-python") {
+            None => None,
+            Some(value) => Some(
+                value
+                    .as_str()
+                    .ok_or("requires-python must be a string")?
+                    .to_string(),
+            ),
+        };
+        let dependencies = match table.get("dependencies") {
+            None => Vec::new(),
+            Some(value) => value
+                .as_array()
+                .ok_or("dependencies must be an array")?
+                .iter()
+                .map(|dependency| {
+                    dependency
+                        .as_str()
+                        .map(str::to_string)
+                        .ok_or("dependencies must be strings")
+                })
+                .collect::<std::result::Result<_, _>>()?,
+        };
+        Ok(Some(ScriptMetadata {
+            requires_python,
+            dependencies,
+        }))
+    }
+
+    /// Computes the hash of the environment the script needs, used with the script path to key
+    /// its venv in the central store.
+    ///
+    /// `python_request` is the request the venv is created for, given explicitly or parsed from
+    /// `requires-python`.
+    pub fn hash(&self, python_request: Option<&PythonRequest>) -> String {
+        let mut fingerprint = Fingerprint::new();
+        for dependency in &self.dependencies {
+            fingerprint.value("script-dependency", dependency);
+        }
+        fingerprint
+            .value(
+                "requires-python",
+                self.requires_python.as_deref().unwrap_or_default(),
+            )
+            .value(
+                "python-request",
+                python_request
+                    .map(|request| request.to_string())
+                    .unwrap_or_default(),
+            );
+        fingerprint.finish()
+    }
+}
+
+/// Finds the metadata block of the given type and returns its content without comment markers.
+///
+/// A block starts with `# /// <type>` and ends with the last `# ///` line of the following
+/// consecutive comment lines.
+fn find_block(content: &str, block_type: &str) -> std::result::Result<Option<String>, String> {
+    let opening = format!("# /// {}", block_type);
+    let lines: Vec<&str> = content.lines().map(|line| line.trim_end()).collect();
+    let mut block = None;
+    let mut index = 0;
+    while index < lines.len() {
+        if lines[index] != opening {
+            index += 1;
+            continue;
+        }
+        if block.is_some() {
+            return Err(format!("multiple {} blocks found", block_type));
+        }
+
+        let comments: Vec<&str> = lines[index + 1..]
+            .iter()
+            .take_while(|line| **line == "#" || line.starts_with("# "))
+            .copied()
+            .collect();
+        let closing = comments
+            .iter()
+            .rposition(|line| *line == "# ///")
+            .ok_or_else(|| format!("unclosed {} block", block_type))?;
+        let toml: Vec<&str> = comments[..closing]
+            .iter()
+            .map(|line| line.strip_prefix("# ").unwrap_or(""))
+            .collect();
+        block = Some(toml.join("\n"));
+        index += closing + 2;
+    }
+    Ok(block)
+}
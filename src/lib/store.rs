@@ -0,0 +1,68 @@
+use crate::discovery::home_dir;
+use crate::fingerprint::Fingerprint;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Returns the cache directory of py_executer.
+///
+/// This is `$PY_EXECUTER_CACHE_DIR`, or the `py_executer` directory under the cache directory,
+/// `$XDG_CACHE_HOME` or `~/.cache` on Linux, `~/Library/Caches` on macOS and `%LOCALAPPDATA%`
+/// on Windows.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("PY_EXECUTER_CACHE_DIR").filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    #[cfg(target_os = "windows")]
+    let cache_dir = env::var_os("LOCALAPPDATA").map(PathBuf::from);
+
+    #[cfg(target_os = "macos")]
+    let cache_dir = home_dir().map(|home| home.join("Library").join("Caches"));
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let cache_dir = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".cache")));
+
+    cache_dir.map(|dir| dir.join("py_executer"))
+}
+
+/// Returns the directory of the central venv store, `venvs` under [`cache_dir`].
+pub fn store_dir() -> Option<PathBuf> {
+    cache_dir().map(|dir| dir.join("venvs"))
+}
+
+/// Returns the path of the venv in the central store for a project and its dependencies.
+///
+/// The directory is named after the project directory, followed by a hash of the project path
+/// and `dependency_hash`, e.g. `myproject-1a2b3c4d5e6f7a8b`. A project whose dependencies
+/// changed gets a new venv, the old one stays until it is removed.
+///
+/// # Returns
+///
+/// The path of the venv, or `None` if the store directory cannot be determined.
+pub fn store_venv_path(project_path: &Path, dependency_hash: &str) -> Option<PathBuf> {
+    let mut fingerprint = Fingerprint::new();
+    fingerprint
+        .value("project", project_path.to_string_lossy().as_bytes())
+        .value("dependencies", dependency_hash);
+    let key = fingerprint.finish();
+    let name = project_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    Some(store_dir()?.join(format!("{}-{}", name, &key[..16])))
+}
+
+/// Returns `true` if files can be created in `dir`.
+///
+/// The permission bits do not tell about read-only mounts or ownership, so a file is created
+/// and removed again.
+pub(crate) fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".py_executer-write-test-{}", std::process::id()));
+    match fs::File::create(&probe) {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
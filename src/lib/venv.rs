@@ -0,0 +1,198 @@
+use crate::discovery::{Interpreter, InterpreterSource, probe_interpreter};
+use crate::version::{PythonRequest, PythonVersion};
+use crate::{Error, Result, get_python_exec_path, warning_println};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The `pyvenv.cfg` file of a virtual environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PyVenvCfg {
+    /// All keys and values of the file.
+    pub values: HashMap<String, String>,
+}
+
+impl PyVenvCfg {
+    /// Reads the `pyvenv.cfg` of the venv, returns `None` if it does not exist.
+    pub fn read(venv_path: &Path) -> Option<Self> {
+        fs::read_to_string(venv_path.join("pyvenv.cfg"))
+            .ok()
+            .map(|content| PyVenvCfg::parse(&content))
+    }
+
+    /// Parses the `key = value` lines of a `pyvenv.cfg`.
+    pub fn parse(content: &str) -> Self {
+        let values = content
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        PyVenvCfg { values }
+    }
+
+    /// Returns the directory of the base interpreter.
+    pub fn home(&self) -> Option<PathBuf> {
+        self.values.get("home").map(PathBuf::from)
+    }
+
+    /// Returns the version of the base interpreter the venv was created with.
+    ///
+    /// `venv` writes it as `version`, uv as `version_info`.
+    pub fn version(&self) -> Option<PythonVersion> {
+        self.values
+            .get("version")
+            .or_else(|| self.values.get("version_info"))
+            .and_then(|version| version.parse().ok())
+    }
+
+    /// Returns whether the venv was created at `venv_path`, if the creation command is recorded.
+    ///
+    /// Python 3.11+ records the command that created the venv, which contains its path.
+    /// The path may contain spaces, so every span of arguments is compared.
+    pub fn was_created_at(&self, venv_path: &Path) -> Option<bool> {
+        let command = self.values.get("command")?;
+        let venv_path = venv_path.canonicalize().ok()?;
+        let args: Vec<&str> = command.split_whitespace().collect();
+        let found = (0..args.len()).any(|start| {
+            (start + 1..=args.len()).any(|end| {
+                Path::new(&args[start..end].join(" "))
+                    .canonicalize()
+                    .is_ok_and(|path| path == venv_path)
+            })
+        });
+        Some(found)
+    }
+
+    /// Returns the prompt given with `--prompt` when the venv was created.
+    pub fn prompt(&self) -> Option<&str> {
+        self.values
+            .get("prompt")
+            .map(|prompt| prompt.trim_matches(|c| c == '\'' || c == '"'))
+    }
+
+    /// Returns `true` if the venv was created by uv.
+    pub fn is_uv(&self) -> bool {
+        self.values.contains_key("uv")
+    }
+}
+
+/// The result of checking a venv.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VenvHealth {
+    /// The venv works, with the probed interpreter.
+    Healthy(Interpreter),
+    /// The venv is broken or does not match the request, with the reason.
+    Broken(String),
+}
+
+impl fmt::Display for VenvHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VenvHealth::Healthy(interpreter) => write!(f, "healthy, {}", interpreter),
+            VenvHealth::Broken(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Checks that a venv can still be used.
+///
+/// The following checks are done:
+///
+/// 1. the `pyvenv.cfg` exists, if `require_cfg` is `true`
+/// 2. the base interpreter directory recorded in `pyvenv.cfg` still exists
+/// 3. the venv was not moved since it was created, e.g. copied from another machine
+/// 4. the Python executable of the venv runs
+/// 5. its `major.minor` version is the one recorded in `pyvenv.cfg`
+/// 6. it satisfies `python_request`, if given
+///
+/// `require_cfg` should be `false` for environments that are not venvs, like conda environments.
+pub fn check_venv(
+    venv_path: &Path,
+    python_request: Option<&PythonRequest>,
+    require_cfg: bool,
+) -> VenvHealth {
+    let cfg = PyVenvCfg::read(venv_path);
+    if let Some(cfg) = &cfg {
+        if let Some(home) = cfg.home()
+            && !home.exists()
+        {
+            return VenvHealth::Broken(format!(
+                "its base interpreter directory {} does not exist anymore",
+                home.display()
+            ));
+        }
+        if cfg.was_created_at(venv_path) == Some(false) {
+            return VenvHealth::Broken("it was created at another location and moved".to_string());
+        }
+    } else if require_cfg {
+        return VenvHealth::Broken("it has no pyvenv.cfg".to_string());
+    }
+
+    let python_exec_path = get_python_exec_path(venv_path);
+    let Some(interpreter) = probe_interpreter(&python_exec_path, InterpreterSource::Path) else {
+        return VenvHealth::Broken(format!("{} does not run", python_exec_path.display()));
+    };
+
+    if let Some(version) = cfg.as_ref().and_then(PyVenvCfg::version)
+        && (version.major, version.minor) != (interpreter.version.major, interpreter.version.minor)
+    {
+        return VenvHealth::Broken(format!(
+            "it was created for python {} but runs python {}",
+            version, interpreter.version
+        ));
+    }
+
+    if let Some(python_request) = python_request
+        && !python_request.matches(&interpreter)
+    {
+        return VenvHealth::Broken(format!(
+            "its python {} does not satisfy the request {}",
+            interpreter.version, python_request
+        ));
+    }
+
+    VenvHealth::Healthy(interpreter)
+}
+
+/// Makes sure pip is available in the venv, bootstrapping it with `ensurepip` if needed.
+///
+/// Venvs created by uv without `--seed` have no pip, which is needed to install the
+/// dependencies when uv is not available.
+///
+/// # Errors
+///
+/// The function returns [`Error::DependencySyncFailed`] if `ensurepip` fails, e.g. because the
+/// base interpreter was installed without it, or [`Error::SpawnFailed`] if it cannot be executed.
+pub fn ensure_pip(python_exec_path: &Path, quiet: bool) -> Result<()> {
+    let has_pip = Command::new(python_exec_path)
+        .args(["-m", "pip", "--version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if has_pip {
+        return Ok(());
+    }
+
+    if !quiet {
+        warning_println!("No pip found in venv, will bootstrap it with ensurepip");
+    }
+    let output = Command::new(python_exec_path)
+        .args(["-m", "ensurepip", "--upgrade"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|source| Error::SpawnFailed {
+            program: "ensurepip".to_string(),
+            source,
+        })?;
+    if !output.status.success() {
+        return Err(Error::DependencySyncFailed {
+            tool: "ensurepip".to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+    Ok(())
+}
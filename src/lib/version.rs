@@ -0,0 +1,391 @@
+use crate::discovery::Interpreter;
+use crate::pyproject::PyProject;
+use crate::{Error, warning_println};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A Python version, e.g. `3.11.7`.
+///
+/// Pre-release suffixes like `3.13.0rc1` are ignored when parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PythonVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl PythonVersion {
+    /// Creates a new version.
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        PythonVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for PythonVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for PythonVersion {
+    type Err = String;
+
+    /// Parses a version like `3`, `3.11` or `3.11.7`, missing parts are `0`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, '.').map(leading_number);
+        let major = parts
+            .next()
+            .flatten()
+            .ok_or_else(|| format!("Invalid python version: {}", s))?;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Ok(PythonVersion::new(major, minor, patch))
+    }
+}
+
+/// Parses the leading digits of a version part, e.g. `0` of `0rc1`.
+fn leading_number(part: &str) -> Option<u32> {
+    let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// A comparison operator of a version specifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    LessEqual,
+    Less,
+    Compatible,
+}
+
+/// A single version specifier like `>=3.10` or `==3.11.*`, following PEP 440.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSpecifier {
+    pub operator: Operator,
+    pub version: PythonVersion,
+    /// The number of version parts given, e.g. `2` for `3.11`.
+    pub parts: usize,
+    /// Whether the version ends with `.*`.
+    pub wildcard: bool,
+}
+
+impl VersionSpecifier {
+    /// Returns `true` if `version` satisfies the specifier.
+    pub fn contains(&self, version: &PythonVersion) -> bool {
+        match self.operator {
+            Operator::Equal if self.wildcard => self.prefix_matches(version),
+            Operator::Equal => *version == self.version,
+            Operator::NotEqual if self.wildcard => !self.prefix_matches(version),
+            Operator::NotEqual => *version != self.version,
+            Operator::GreaterEqual => *version >= self.version,
+            Operator::Greater => *version > self.version,
+            Operator::LessEqual => *version <= self.version,
+            Operator::Less => *version < self.version,
+            Operator::Compatible => {
+                // ~=3.11 is >=3.11 and ==3.*, ~=3.11.2 is >=3.11.2 and ==3.11.*
+                let prefix = VersionSpecifier {
+                    operator: Operator::Equal,
+                    version: self.version,
+                    parts: self.parts.saturating_sub(1).max(1),
+                    wildcard: true,
+                };
+                *version >= self.version && prefix.prefix_matches(version)
+            }
+        }
+    }
+
+    fn prefix_matches(&self, version: &PythonVersion) -> bool {
+        let expected = [self.version.major, self.version.minor, self.version.patch];
+        let actual = [version.major, version.minor, version.patch];
+        expected[..self.parts.min(3)] == actual[..self.parts.min(3)]
+    }
+}
+
+impl fmt::Display for VersionSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operator = match self.operator {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::GreaterEqual => ">=",
+            Operator::Greater => ">",
+            Operator::LessEqual => "<=",
+            Operator::Less => "<",
+            Operator::Compatible => "~=",
+        };
+        write!(
+            f,
+            "{}{}",
+            operator,
+            version_prefix(&self.version, self.parts)
+        )?;
+        if self.wildcard {
+            f.write_str(".*")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the first `parts` parts of `version`, e.g. `3.11` for two parts.
+fn version_prefix(version: &PythonVersion, parts: usize) -> String {
+    [version.major, version.minor, version.patch][..parts.clamp(1, 3)]
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl FromStr for VersionSpecifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let operators = [
+            ("~=", Operator::Compatible),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            (">=", Operator::GreaterEqual),
+            ("<=", Operator::LessEqual),
+            (">", Operator::Greater),
+            ("<", Operator::Less),
+        ];
+        let (operator, rest) = operators
+            .iter()
+            .find_map(|(prefix, operator)| s.strip_prefix(prefix).map(|rest| (*operator, rest)))
+            .ok_or_else(|| format!("Invalid version specifier: {}", s))?;
+        let rest = rest.trim();
+        let (rest, wildcard) = match rest.strip_suffix(".*") {
+            Some(rest) => (rest, true),
+            None => (rest, false),
+        };
+        if wildcard && !matches!(operator, Operator::Equal | Operator::NotEqual) {
+            return Err(format!("Invalid version specifier: {}", s));
+        }
+        let version = rest
+            .parse()
+            .map_err(|_| format!("Invalid version specifier: {}", s))?;
+        Ok(VersionSpecifier {
+            operator,
+            version,
+            parts: rest.split('.').count(),
+            wildcard,
+        })
+    }
+}
+
+/// A comma-separated list of version specifiers, e.g. `>=3.10,<3.13`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VersionSpecifiers(pub Vec<VersionSpecifier>);
+
+impl VersionSpecifiers {
+    /// Returns `true` if `version` satisfies all specifiers.
+    pub fn contains(&self, version: &PythonVersion) -> bool {
+        self.0.iter().all(|specifier| specifier.contains(version))
+    }
+}
+
+impl fmt::Display for VersionSpecifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let specifiers: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&specifiers.join(","))
+    }
+}
+
+impl FromStr for VersionSpecifiers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|part| !part.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(VersionSpecifiers)
+    }
+}
+
+/// A request for a Python interpreter, as given to `--python`.
+///
+/// The same syntax is used in `.python-version` files and is passed to `uv venv --python`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PythonRequest {
+    /// An implementation and/or a version, e.g. `3.11`, `>=3.10,<3.13` or `pypy3.10`.
+    Version {
+        /// The requested implementation, e.g. `pypy`, `None` for any.
+        implementation: Option<String>,
+        specifiers: VersionSpecifiers,
+    },
+    /// The path of an interpreter.
+    Path(PathBuf),
+}
+
+impl PythonRequest {
+    /// Parses a request.
+    ///
+    /// A request can be a path, a version like `3.11` or `3.11.7`, version specifiers like
+    /// `>=3.10,<3.13`, or an implementation name followed by a version like `pypy3.10` or
+    /// `cpython@3.12`. A bare version matches all versions starting with it, so `3.11` matches
+    /// `3.11.7`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an `Err` describing the problem if the request cannot be parsed.
+    pub fn parse(request: &str) -> Result<Self, String> {
+        let request = request.trim();
+        if request.is_empty() {
+            return Err("Empty python request".to_string());
+        }
+        if request.contains(std::path::MAIN_SEPARATOR) || request.contains('/') {
+            return Ok(PythonRequest::Path(PathBuf::from(request)));
+        }
+        if request.starts_with(['=', '!', '<', '>', '~']) {
+            return Ok(PythonRequest::Version {
+                implementation: None,
+                specifiers: request.parse()?,
+            });
+        }
+
+        let name_len = request
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(request.len());
+        let (name, version) = request.split_at(name_len);
+        let name = name.trim_end_matches('@').to_ascii_lowercase();
+        let implementation = match name.as_str() {
+            "" | "python" => None,
+            "cpython" | "pypy" | "graalpy" => Some(name),
+            _ => return Err(format!("Unknown python implementation: {}", name)),
+        };
+        if version.is_empty() {
+            return Ok(PythonRequest::Version {
+                implementation,
+                specifiers: VersionSpecifiers::default(),
+            });
+        }
+        if !version.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return Err(format!("Invalid python version: {}", version));
+        }
+        let specifier = VersionSpecifier {
+            operator: Operator::Equal,
+            version: version.parse()?,
+            parts: version.split('.').count(),
+            wildcard: true,
+        };
+        Ok(PythonRequest::Version {
+            implementation,
+            specifiers: VersionSpecifiers(vec![specifier]),
+        })
+    }
+
+    /// Returns `true` if the interpreter satisfies the request.
+    pub fn matches(&self, interpreter: &Interpreter) -> bool {
+        match self {
+            PythonRequest::Version {
+                implementation,
+                specifiers,
+            } => {
+                implementation
+                    .as_ref()
+                    .is_none_or(|implementation| *implementation == interpreter.implementation)
+                    && specifiers.contains(&interpreter.version)
+            }
+            PythonRequest::Path(path) => {
+                path == &interpreter.path
+                    || path.canonicalize().ok().as_ref() == Some(&interpreter.executable)
+            }
+        }
+    }
+}
+
+impl fmt::Display for PythonRequest {
+    /// Formats the request in the syntax understood by `uv venv --python`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PythonRequest::Path(path) => write!(f, "{}", path.display()),
+            PythonRequest::Version {
+                implementation,
+                specifiers,
+            } => {
+                let implementation = implementation.as_deref().unwrap_or("");
+                match specifiers.0.as_slice() {
+                    [] if implementation.is_empty() => f.write_str("python"),
+                    [] => f.write_str(implementation),
+                    [specifier] if specifier.operator == Operator::Equal && specifier.wildcard => {
+                        let version = version_prefix(&specifier.version, specifier.parts);
+                        write!(f, "{}{}", implementation, version)
+                    }
+                    _ if implementation.is_empty() => write!(f, "{}", specifiers),
+                    _ => write!(f, "{}@{}", implementation, specifiers),
+                }
+            }
+        }
+    }
+}
+
+/// Finds the python request of a project, if no request was given explicitly.
+///
+/// The `.python-version` file of the project is used first, then the `requires-python` of
+/// the `[project]` table of its `pyproject.toml`.
+///
+/// The first entry of `.python-version` that is a valid request is used. pyenv also writes
+/// entries like `system` or the name of a virtualenv there, they are skipped with a warning.
+///
+/// # Returns
+///
+/// The request and the path of the file it was read from, or `None` if the project does
+/// not request a Python version.
+///
+/// # Errors
+///
+/// The function returns [`Error::InvalidPythonRequest`] if the `requires-python` cannot be
+/// parsed, or [`Error::InvalidPyProject`] if the `pyproject.toml` cannot be read.
+pub fn find_project_python_request(
+    project: &Path,
+    quiet: bool,
+) -> crate::Result<Option<(PythonRequest, PathBuf)>> {
+    let python_version_path = project.join(".python-version");
+    if let Ok(content) = fs::read_to_string(&python_version_path) {
+        let entries = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        for entry in entries {
+            match PythonRequest::parse(entry) {
+                Ok(request) => return Ok(Some((request, python_version_path))),
+                Err(reason) => {
+                    if !quiet {
+                        warning_println!(
+                            "Ignoring {} in {}: {}",
+                            entry,
+                            python_version_path.display(),
+                            reason
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let pyproject_path = project.join("pyproject.toml");
+    if pyproject_path.exists() {
+        let pyproject = PyProject::read(&pyproject_path)?;
+        if let Some(requires_python) = pyproject.requires_python() {
+            return parse_request(requires_python).map(|request| Some((request, pyproject_path)));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses a request, mapping the failure to [`Error::InvalidPythonRequest`].
+pub fn parse_request(request: &str) -> crate::Result<PythonRequest> {
+    PythonRequest::parse(request).map_err(|reason| Error::InvalidPythonRequest {
+        request: request.to_string(),
+        reason,
+    })
+}
@@ -0,0 +1,200 @@
+use crate::pyproject::PyProject;
+use crate::{Error, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A member project of a uv workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceMember {
+    /// The `[project].name` of the member.
+    pub name: String,
+    /// The directory of the member.
+    pub path: PathBuf,
+}
+
+/// A uv workspace, declared by `[tool.uv.workspace]` in the `pyproject.toml` of its root.
+///
+/// ```toml
+/// [tool.uv.workspace]
+/// members = ["packages/*"]
+/// exclude = ["packages/legacy"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    /// The root directory of the workspace, where `uv.lock` and the venv are.
+    pub root: PathBuf,
+    /// The member projects, including the root if it has a `[project]` table.
+    pub members: Vec<WorkspaceMember>,
+}
+
+impl Workspace {
+    /// Finds the uv workspace the project at `project_path` belongs to.
+    ///
+    /// The directories from `project_path` upwards are searched for a `pyproject.toml` with a
+    /// `[tool.uv.workspace]` table. The project belongs to the nearest such workspace if it is
+    /// its root or one of its members, like uv decides it.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [`Error::InvalidPyProject`] if the `pyproject.toml` of the workspace
+    /// root or of a member cannot be read, or [`Error::InvalidWorkspace`] if its workspace table
+    /// is invalid.
+    pub fn find(project_path: &Path) -> Result<Option<Self>> {
+        for dir in project_path.ancestors() {
+            // unrelated pyproject.toml files above the project are not required to be valid
+            let Ok(pyproject) = PyProject::read(&dir.join("pyproject.toml")) else {
+                continue;
+            };
+            if pyproject.get("tool.uv.workspace").is_none() {
+                continue;
+            }
+            let workspace = Workspace::read(dir, &pyproject)?;
+            let belongs = dir == project_path
+                || workspace
+                    .members
+                    .iter()
+                    .any(|member| member.path == project_path);
+            return Ok(belongs.then_some(workspace));
+        }
+        Ok(None)
+    }
+
+    /// Reads the workspace rooted at `root` with the parsed `pyproject.toml` of the root.
+    ///
+    /// The `members` and `exclude` patterns support `*` and `?` in each path component.
+    ///
+    /// # Errors
+    ///
+    /// See [`Workspace::find`].
+    pub fn read(root: &Path, pyproject: &PyProject) -> Result<Self> {
+        let invalid = |reason: String| Error::InvalidWorkspace {
+            path: pyproject.path.clone(),
+            reason,
+        };
+        let patterns = |key: &str| match pyproject.get(&format!("tool.uv.workspace.{}", key)) {
+            None => Ok(Vec::new()),
+            Some(value) => value
+                .as_array()
+                .and_then(|values| values.iter().map(|value| value.as_str()).collect())
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "tool.uv.workspace.{} must be an array of strings",
+                        key
+                    ))
+                }),
+        };
+        let excluded: Vec<PathBuf> = patterns("exclude")?
+            .into_iter()
+            .flat_map(|pattern| expand_pattern(root, pattern))
+            .collect();
+
+        let mut members = Vec::new();
+        if let Some(name) = pyproject.project_name() {
+            members.push(WorkspaceMember {
+                name: name.to_string(),
+                path: root.to_path_buf(),
+            });
+        }
+        for pattern in patterns("members")? {
+            for path in expand_pattern(root, pattern) {
+                let member_pyproject = path.join("pyproject.toml");
+                if excluded.contains(&path)
+                    || !member_pyproject.exists()
+                    || members
+                        .iter()
+                        .any(|member: &WorkspaceMember| member.path == path)
+                {
+                    continue;
+                }
+                let member_pyproject = PyProject::read(&member_pyproject)?;
+                let name = member_pyproject.project_name().ok_or_else(|| {
+                    invalid(format!("member {} has no project name", path.display()))
+                })?;
+                members.push(WorkspaceMember {
+                    name: name.to_string(),
+                    path,
+                });
+            }
+        }
+        Ok(Workspace {
+            root: root.to_path_buf(),
+            members,
+        })
+    }
+
+    /// Returns the member with the given name.
+    pub fn member(&self, name: &str) -> Option<&WorkspaceMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+
+    /// Returns the innermost member whose directory contains `path`.
+    pub fn member_of(&self, path: &Path) -> Option<&WorkspaceMember> {
+        self.members
+            .iter()
+            .filter(|member| path.starts_with(&member.path))
+            .max_by_key(|member| member.path.components().count())
+    }
+}
+
+/// Returns the directories under `root` matching the `/`-separated `pattern`.
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut paths = vec![root.to_path_buf()];
+    for component in pattern.split('/').filter(|component| !component.is_empty()) {
+        if component == "." {
+            continue;
+        }
+        if !component.contains(['*', '?']) {
+            paths = paths
+                .into_iter()
+                .map(|path| path.join(component))
+                .filter(|path| path.is_dir())
+                .collect();
+            continue;
+        }
+        let mut matches = Vec::new();
+        for path in paths {
+            let Ok(entries) = fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                if entry.path().is_dir() && glob_match(component, &name.to_string_lossy()) {
+                    matches.push(entry.path());
+                }
+            }
+        }
+        matches.sort();
+        paths = matches;
+    }
+    paths
+}
+
+/// Matches `text` against a glob `pattern` with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // the position of the last `*` in the pattern and the text position it matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,9 +1,13 @@
 mod python;
 mod uv;
+mod venvs;
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 use std::process;
+use std::time::Duration;
+
+use py_executer_lib::runner::RunSpec;
 
 use python::python;
 use uv::uv;
@@ -18,59 +22,273 @@ struct Args {
 #[derive(Subcommand)]
 enum Commands {
     /// Run script mode
-    Run {
-        /// Script path
-        #[clap(value_name = "SCRIPT")]
-        script: PathBuf,
-
-        /// Project path
-        #[clap(short, long, default_value = ".")]
-        project: PathBuf,
-
-        /// Additional environment variables in the format KEY=VALUE (can be used multiple times)
-        #[clap(short = 'E', long)]
-        env: Vec<String>,
-
-        /// .env file path, if provided, it will be loaded.
-        /// If a .env file is found under --project path, it will be loaded automatically
-        #[clap(short = 'e', long)]
-        env_file: Option<PathBuf>,
-
-        /// Suppress output
-        #[clap(long, default_value_t = false)]
-        quiet: bool,
-
-        /// Clean mode
-        /// if specified, it will clean the created uv .venv and configs
-        /// if those files originally exist, they will not be deleted
-        #[clap(long, default_value_t = false)]
-        clean: bool,
-
-        /// Python arguments, must be placed as the last argument after --
-        #[arg(num_args(0..), trailing_var_arg = true, allow_hyphen_values = true)]
-        py_args: Vec<String>,
-    },
+    Run(Box<RunArgs>),
     /// UV mode - pass all arguments to uv command
     Uv {
         /// Arguments to pass to uv command
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
+    /// Manage the venvs created by py_executer
+    Venvs {
+        #[clap(subcommand)]
+        command: VenvsCommands,
+    },
 }
 
-fn main() -> process::ExitCode {
-    let args = Args::parse();
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Script path
+    #[clap(value_name = "SCRIPT")]
+    script: PathBuf,
 
-    match args.command {
-        Commands::Run {
+    /// Project path.
+    /// If not provided, the nearest directory above the script with a pyproject.toml,
+    /// requirements.txt, Pipfile, environment.yml, .venv, venv or .git is used, otherwise the
+    /// script directory
+    #[clap(short, long)]
+    project: Option<PathBuf>,
+
+    /// Additional environment variables in the format KEY=VALUE (can be used multiple times)
+    #[clap(short = 'E', long)]
+    env: Vec<String>,
+
+    /// .env file path, if provided, it will be loaded.
+    /// If a .env file is found under --project path, it will be loaded automatically
+    #[clap(short = 'e', long)]
+    env_file: Option<PathBuf>,
+
+    /// Additional entry to append to PYTHONPATH (can be used multiple times).
+    /// The project and its src directory, for a src layout, are always appended
+    #[clap(long, value_name = "PATH")]
+    pythonpath: Vec<PathBuf>,
+
+    /// Do not append the project and --pythonpath entries to PYTHONPATH
+    #[clap(long, default_value_t = false, conflicts_with = "pythonpath")]
+    no_pythonpath: bool,
+
+    /// Requirements file to install (can be used multiple times).
+    /// If not provided, requirements.txt under --project is installed if it exists
+    #[clap(short = 'r', long, value_name = "FILE")]
+    requirements: Vec<PathBuf>,
+
+    /// Constraints file applied to every pip install (can be used multiple times)
+    #[clap(short = 'c', long, value_name = "FILE")]
+    constraints: Vec<PathBuf>,
+
+    /// Optional dependencies of the project to install with uv sync (can be used multiple times).
+    /// Extras and groups not given are read from [tool.py_executer] in pyproject.toml
+    #[clap(long, value_name = "EXTRA", conflicts_with = "all_extras")]
+    extra: Vec<String>,
+
+    /// Install all optional dependencies of the project with uv sync
+    #[clap(long, default_value_t = false)]
+    all_extras: bool,
+
+    /// Dependency groups of the project to install with uv sync (can be used multiple times)
+    #[clap(long, value_name = "GROUP")]
+    group: Vec<String>,
+
+    /// Do not install the dev dependency group with uv sync
+    #[clap(long, default_value_t = false)]
+    no_dev: bool,
+
+    /// Install only the given dependency groups, without the project (can be used multiple times)
+    #[clap(long, value_name = "GROUP")]
+    only_group: Vec<String>,
+
+    /// Fail if uv.lock is missing or out of date with pyproject.toml, instead of updating it
+    #[clap(long, default_value_t = false, conflicts_with = "frozen")]
+    locked: bool,
+
+    /// Install uv.lock as is, without checking it against pyproject.toml
+    #[clap(long, default_value_t = false)]
+    frozen: bool,
+
+    /// Do not access the network, only use the uv cache and --find-links.
+    /// Without uv, pip is run with --no-index
+    #[clap(long, default_value_t = false)]
+    offline: bool,
+
+    /// Local directory or URL to look for packages in (can be used multiple times)
+    #[clap(long, value_name = "PATH-OR-URL")]
+    find_links: Vec<String>,
+
+    /// Member of the uv workspace to sync and run.
+    /// If not provided, the member whose directory contains the script is used
+    #[clap(long, value_name = "NAME")]
+    package: Option<String>,
+
+    /// Python interpreter to use, e.g. 3.11, ">=3.10,<3.13", pypy3.10 or a path.
+    /// If not provided, .python-version and requires-python of pyproject.toml under --project
+    /// are used, or requires-python of the inline script metadata (PEP 723) of the script
+    #[clap(long, value_name = "VERSION-SPEC")]
+    python: Option<String>,
+
+    /// Virtual environment to use.
+    /// If not provided, an activated venv (VIRTUAL_ENV) or conda environment (CONDA_PREFIX) is used,
+    /// then the conda environment of an environment.yml under --project, then a venv or .venv
+    /// under --project, otherwise a new .venv is created
+    #[clap(long, value_name = "PATH")]
+    venv: Option<PathBuf>,
+
+    /// Recreate the project venv if it is broken or does not match the requested python,
+    /// instead of failing
+    #[clap(long, default_value_t = false)]
+    rebuild_venv: bool,
+
+    /// Sync the dependencies even if pyproject.toml, uv.lock, requirements.txt and the
+    /// python did not change since the last sync
+    #[clap(long, default_value_t = false)]
+    force_sync: bool,
+
+    /// Create the venv in the central store under the cache directory instead of a .venv in
+    /// --project, reused across runs with the same project and dependencies
+    #[clap(long, default_value_t = false)]
+    central_venv: bool,
+
+    /// Seconds to wait for another py_executer process preparing the same venv
+    #[clap(long, value_name = "SECONDS", default_value_t = 300)]
+    lock_timeout: u64,
+
+    /// Suppress output
+    #[clap(long, default_value_t = false)]
+    quiet: bool,
+
+    /// Clean mode
+    /// if specified, it will clean the created uv .venv and configs
+    /// if those files originally exist, they will not be deleted
+    #[clap(long, default_value_t = false)]
+    clean: bool,
+
+    /// Seconds the script is given to exit after a forwarded SIGINT, SIGTERM or SIGHUP,
+    /// before it is killed
+    #[clap(long, value_name = "SECONDS", default_value_t = 10)]
+    grace_period: u64,
+
+    /// Python arguments, must be placed as the last argument after --
+    #[arg(num_args(0..), trailing_var_arg = true, allow_hyphen_values = true)]
+    py_args: Vec<String>,
+}
+
+impl RunArgs {
+    /// Builds the spec of the run from the command line options.
+    fn into_spec(self) -> RunSpec {
+        let RunArgs {
             script,
             project,
             env,
             env_file,
+            pythonpath,
+            no_pythonpath,
+            requirements,
+            constraints,
+            extra,
+            all_extras,
+            group,
+            no_dev,
+            only_group,
+            locked,
+            frozen,
+            offline,
+            find_links,
+            package,
+            python: python_request,
+            venv,
+            rebuild_venv,
+            force_sync,
+            central_venv,
+            lock_timeout,
             quiet,
             clean,
+            grace_period,
             py_args,
-        } => python(script, project, env, env_file, quiet, clean, py_args),
+        } = self;
+        let mut spec = RunSpec::new(script)
+            .envs(env)
+            .no_pythonpath(no_pythonpath)
+            .all_extras(all_extras)
+            .no_dev(no_dev)
+            .locked(locked)
+            .frozen(frozen)
+            .offline(offline)
+            .rebuild_venv(rebuild_venv)
+            .force_sync(force_sync)
+            .central_venv(central_venv)
+            .lock_timeout(Duration::from_secs(lock_timeout))
+            .quiet(quiet)
+            .clean(clean)
+            .forward_signals(true)
+            .grace_period(Duration::from_secs(grace_period))
+            .args(py_args);
+        for pythonpath in pythonpath {
+            spec = spec.pythonpath(pythonpath);
+        }
+        for requirements in requirements {
+            spec = spec.requirements(requirements);
+        }
+        for constraints in constraints {
+            spec = spec.constraints(constraints);
+        }
+        for extra in extra {
+            spec = spec.extra(extra);
+        }
+        for group in group {
+            spec = spec.group(group);
+        }
+        for group in only_group {
+            spec = spec.only_group(group);
+        }
+        for find_links in find_links {
+            spec = spec.find_links(find_links);
+        }
+        if let Some(project) = project {
+            spec = spec.project(project);
+        }
+        if let Some(env_file) = env_file {
+            spec = spec.env_file(env_file);
+        }
+        if let Some(package) = package {
+            spec = spec.package(package);
+        }
+        if let Some(python_request) = python_request {
+            spec = spec.python(python_request);
+        }
+        if let Some(venv) = venv {
+            spec = spec.venv(venv);
+        }
+        spec
+    }
+}
+
+#[derive(Subcommand)]
+enum VenvsCommands {
+    /// List the venvs with their python version, size, last use and project
+    List,
+    /// Remove the venvs that were not used for a while
+    Gc {
+        /// Minimum time since the last use, e.g. 30d, 12h or 2w
+        #[clap(long, value_name = "DURATION", default_value = "30d")]
+        older_than: String,
+
+        /// Only print the venvs that would be removed
+        #[clap(long, default_value_t = false)]
+        dry_run: bool,
+    },
+}
+
+fn main() -> process::ExitCode {
+    let args = Args::parse();
+
+    match args.command {
+        Commands::Run(run_args) => python(run_args.into_spec()),
         Commands::Uv { args } => uv(args),
+        Commands::Venvs { command } => match command {
+            VenvsCommands::List => venvs::list(),
+            VenvsCommands::Gc {
+                older_than,
+                dry_run,
+            } => venvs::gc(&older_than, dry_run),
+        },
     }
 }
@@ -1,38 +1,47 @@
-use py_executer_lib::get_uv_path;
+use py_executer_lib::process::{ForwardingChild, exit_code, to_process_exit_code};
+use py_executer_lib::{error_println, get_uv_path, uv_install_hint};
 use std::process;
 use std::process::{Command, Stdio};
 
 /// Execute a command with the `uv` command.
 ///
+/// The stdio of `uv` is inherited, so its output is streamed live. Termination signals
+/// received by `py_executer` are forwarded to `uv`.
+///
 /// # Arguments
 ///
 /// * `args`: The arguments to pass to `uv`.
 ///
 /// # Return value
 ///
-/// The exit code of the spawned process.
+/// The exact exit code of `uv`, or `128 + signum` if it was killed by a signal.
 ///
 /// # Errors
 ///
-/// If the `uv` command cannot be executed, an error message is printed to
-/// stderr and the process exits with a status code of 1.
+/// If `uv` is not installed or cannot be executed, an error message is printed to
+/// stderr and `FAILURE` is returned.
 pub fn uv(args: Vec<String>) -> process::ExitCode {
-    let cmd = Command::new(get_uv_path().unwrap())
-        .args(&args)
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .output();
-    match cmd {
-        Ok(status) => {
-            if status.status.success() {
-                process::ExitCode::FAILURE
-            } else {
-                process::ExitCode::SUCCESS
-            }
+    let uv_path = match get_uv_path() {
+        Ok(uv_path) => uv_path,
+        Err(err) => {
+            error_println!("{}", err);
+            eprintln!("Please run the following command to install uv:");
+            eprintln!("{}", uv_install_hint());
+            return process::ExitCode::FAILURE;
         }
+    };
+
+    let mut cmd = Command::new(&uv_path);
+    cmd.args(&args)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    let status = ForwardingChild::spawn(&mut cmd).and_then(|mut child| child.wait());
+    match status {
+        Ok(status) => to_process_exit_code(exit_code(&status)),
         Err(e) => {
-            eprintln!("Failed to execute uv: {}", e);
-            process::exit(1);
+            error_println!("Failed to execute uv: {}", e);
+            process::ExitCode::FAILURE
         }
     }
 }
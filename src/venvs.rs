@@ -0,0 +1,249 @@
+use colored::*;
+use py_executer_lib::lock::{FileLock, lock_path, venv_use_lock_path};
+use py_executer_lib::registry::{VenvRecord, VenvRegistry, dir_size, parse_duration};
+use py_executer_lib::venv::PyVenvCfg;
+use py_executer_lib::{error_println, warning_println};
+use std::fs;
+use std::process;
+use std::time::{Duration, SystemTime};
+
+/// List the venvs created by py_executer.
+///
+/// For each venv, its path, python version, size, last use and project are printed.
+/// Venvs that were removed by other means are marked as missing.
+///
+/// # Errors
+///
+/// If the registry cannot be read, an error message is printed to stderr and `FAILURE`
+/// is returned.
+pub fn list() -> process::ExitCode {
+    let registry = match VenvRegistry::open() {
+        Ok(registry) => registry,
+        Err(err) => {
+            error_println!("{}", err);
+            return process::ExitCode::FAILURE;
+        }
+    };
+    if registry.records().is_empty() {
+        println!("No venvs created by py_executer found");
+        return process::ExitCode::SUCCESS;
+    }
+
+    let rows: Vec<[String; 5]> = registry
+        .records()
+        .iter()
+        .map(|record| {
+            let exists = record.path.exists();
+            let version = PyVenvCfg::read(&record.path)
+                .and_then(|cfg| cfg.version())
+                .map(|version| version.to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let size = if exists {
+                format_size(dir_size(&record.path))
+            } else {
+                "missing".to_string()
+            };
+            [
+                record.path.display().to_string(),
+                version,
+                size,
+                format_age(record.last_used),
+                record.project.display().to_string(),
+            ]
+        })
+        .collect();
+
+    let header = ["PATH", "PYTHON", "SIZE", "LAST USED", "PROJECT"];
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain([header[column].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let format_row = |row: &[&str]| {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        line.join("  ").trim_end().to_string()
+    };
+    println!("{}", format_row(&header).bold());
+    for row in &rows {
+        println!("{}", format_row(&row.each_ref().map(String::as_str)));
+    }
+    process::ExitCode::SUCCESS
+}
+
+/// Remove the venvs created by py_executer that were not used for longer than `older_than`.
+///
+/// Records of venvs that were already removed by other means are dropped as well. Venvs that
+/// are being prepared or used by another run are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `older_than`: The minimum time since the last use, e.g. `30d`.
+/// * `dry_run`: If `true`, only print what would be removed.
+///
+/// # Errors
+///
+/// If `older_than` is invalid or the registry cannot be read or written, an error message is
+/// printed to stderr and `FAILURE` is returned.
+pub fn gc(older_than: &str, dry_run: bool) -> process::ExitCode {
+    let age = match parse_duration(older_than) {
+        Ok(age) => age,
+        Err(err) => {
+            error_println!("Invalid --older-than {}: {}", older_than, err);
+            return process::ExitCode::FAILURE;
+        }
+    };
+    // keep concurrent runs from recording venvs while the registry is rewritten
+    let _lock = match VenvRegistry::lock() {
+        Ok(lock) => lock,
+        Err(err) => {
+            error_println!("{}", err);
+            return process::ExitCode::FAILURE;
+        }
+    };
+    let mut registry = match VenvRegistry::open() {
+        Ok(registry) => registry,
+        Err(err) => {
+            error_println!("{}", err);
+            return process::ExitCode::FAILURE;
+        }
+    };
+
+    let expired: Vec<VenvRecord> = registry
+        .records()
+        .iter()
+        .filter(|record| !record.path.exists() || record.is_older_than(age))
+        .cloned()
+        .collect();
+    let mut removed = 0;
+    let mut freed = 0;
+    for record in &expired {
+        if !record.path.exists() {
+            println!("Forgetting missing venv {}", record.path.display());
+        } else if !record.path.join("pyvenv.cfg").exists() {
+            // never remove a directory that is not a venv anymore
+            warning_println!(
+                "{} is not a venv anymore, will only forget it",
+                record.path.display()
+            );
+        } else {
+            let _locks = if dry_run {
+                None
+            } else {
+                match lock_for_removal(record) {
+                    Some(locks) => Some(locks),
+                    None => continue,
+                }
+            };
+            let size = dir_size(&record.path);
+            println!(
+                "{} {} ({}, last used {})",
+                if dry_run { "Would remove" } else { "Removing" },
+                record.path.display(),
+                format_size(size),
+                format_age(record.last_used)
+            );
+            if !dry_run && let Err(err) = fs::remove_dir_all(&record.path) {
+                error_println!("Failed to remove {}: {}", record.path.display(), err);
+                continue;
+            }
+            removed += 1;
+            freed += size;
+        }
+        if !dry_run {
+            registry.remove(&record.path);
+        }
+    }
+
+    if !dry_run
+        && !expired.is_empty()
+        && let Err(err) = registry.save()
+    {
+        error_println!("{}", err);
+        return process::ExitCode::FAILURE;
+    }
+    println!(
+        "{} {} venv(s), {} {}",
+        if dry_run { "Would remove" } else { "Removed" },
+        removed,
+        format_size(freed),
+        if dry_run { "would be freed" } else { "freed" }
+    );
+    process::ExitCode::SUCCESS
+}
+
+/// Locks the venv of `record` for its removal, like the cleanup of a `--clean` run.
+///
+/// The lock of the project is taken first, then the use lock of the venv, so no run starts or
+/// keeps using the venv. Neither is waited for, as the registry lock is held and a run that is
+/// preparing the venv may need it to record the venv.
+///
+/// # Returns
+///
+/// The held locks, or `None` after printing a warning if the venv is busy.
+fn lock_for_removal(record: &VenvRecord) -> Option<(FileLock, FileLock)> {
+    let busy = || {
+        warning_println!(
+            "Skipping {}, it is used by another py_executer process",
+            record.path.display()
+        );
+    };
+    let project_lock = match FileLock::acquire(&lock_path(record.lock_key()), Duration::ZERO, || {})
+    {
+        Ok(lock) => lock,
+        Err(_) => {
+            busy();
+            return None;
+        }
+    };
+    match FileLock::acquire(&venv_use_lock_path(&record.path), Duration::ZERO, || {}) {
+        Ok(use_lock) => Some((project_lock, use_lock)),
+        Err(_) => {
+            busy();
+            None
+        }
+    }
+}
+
+/// Formats a size in bytes with a binary unit, e.g. `12.3 MiB`.
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Formats the time since `time`, e.g. `3 days ago`.
+fn format_age(time: SystemTime) -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let (value, unit) = match elapsed {
+        0..60 => return "just now".to_string(),
+        60..3600 => (elapsed / 60, "minute"),
+        3600..86400 => (elapsed / 3600, "hour"),
+        _ => (elapsed / 86400, "day"),
+    };
+    format!(
+        "{} {}{} ago",
+        value,
+        unit,
+        if value == 1 { "" } else { "s" }
+    )
+}
@@ -1,15 +1,1313 @@
-use py_executer_lib::validate_to_absolute_path;
+use py_executer_lib::{Error, validate_to_absolute_path};
 use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Held by the tests reading or changing the environment variables of the process.
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Returns the preferred native interpreter, `None` if the machine has none.
+fn native_python() -> Option<PathBuf> {
+    py_executer_lib::discovery::find_interpreter(|_| true).map(|interpreter| interpreter.path)
+}
+
+/// Creates a venv without pip at `path` with the native interpreter.
+fn create_test_venv(python: &std::path::Path, path: &std::path::Path) {
+    let status = std::process::Command::new(python)
+        .args(["-m", "venv", "--without-pip"])
+        .arg(path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+/// Writes a stub `uv` running the shell `body` into `dir`.
+///
+/// # Returns
+///
+/// `PATH` with `dir` in front, for the stub to be found first.
+#[cfg(unix)]
+fn stub_uv(dir: &std::path::Path, body: &str) -> std::ffi::OsString {
+    use std::os::unix::fs::PermissionsExt;
+
+    let uv = dir.join("uv");
+    std::fs::write(&uv, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&uv, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    std::env::join_paths(std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(&path)))
+        .unwrap()
+}
 
 #[test]
 fn test_validate_to_absolute_path() {
     let script_path = PathBuf::from("test.py");
     let result = validate_to_absolute_path(&script_path);
     assert!(result.is_ok());
-    println!("Script path: {}", result.unwrap().display().to_string());
+    println!("Script path: {}", result.unwrap().display());
 
     let non_existent_path = PathBuf::from("");
     let result = validate_to_absolute_path(&non_existent_path);
     assert!(result.is_err());
     println!("Error: {}", result.unwrap_err());
 }
+
+#[test]
+fn test_validate_to_absolute_path_error_kind() {
+    let non_existent_path = PathBuf::from("does_not_exist.py");
+    let result = validate_to_absolute_path(&non_existent_path);
+    assert!(matches!(result, Err(Error::ScriptNotFound { .. })));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_exit_code_from_status() {
+    use py_executer_lib::process::{exit_code, exit_signal};
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    let exited = ExitStatus::from_raw(3 << 8);
+    assert_eq!(exit_code(&exited), 3);
+    assert_eq!(exit_signal(&exited), None);
+
+    let killed = ExitStatus::from_raw(9);
+    assert_eq!(exit_code(&killed), 137);
+    assert_eq!(exit_signal(&killed), Some(9));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_forwarding_child() {
+    use py_executer_lib::process::{ForwardingChild, SignalGuard, exit_code};
+    use std::process::Command;
+    use std::time::{Duration, Instant};
+
+    let temp = tempfile::tempdir().unwrap();
+    let ready = temp.path().join("ready");
+    // the child creates `ready` once its trap is installed, then SIGTERM is sent to the
+    // thread of this test only, the other tests never receive it
+    let terminate_when_ready = || {
+        // SAFETY: pthread_self has no memory safety requirements.
+        let thread = unsafe { libc::pthread_self() } as usize;
+        let ready = ready.clone();
+        std::thread::spawn(move || {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !ready.exists() {
+                assert!(Instant::now() < deadline, "the child never got ready");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            // SAFETY: the test thread is alive, it waits for the child until the signal.
+            unsafe { libc::pthread_kill(thread as libc::pthread_t, libc::SIGTERM) };
+        })
+    };
+    let child_command = |trap: &str| {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("trap {} TERM; touch \"$0\"; sleep 5", trap))
+            .arg(&ready);
+        command
+    };
+
+    let guard = SignalGuard::install();
+    let mut child = ForwardingChild::spawn(&mut child_command("'exit 7'")).unwrap();
+    let kill = terminate_when_ready();
+    let status = child.wait().unwrap();
+    kill.join().unwrap();
+    assert_eq!(exit_code(&status), 7);
+    assert_eq!(guard.received(), Some(15));
+
+    std::fs::remove_file(&ready).unwrap();
+    let start = Instant::now();
+    let mut child = ForwardingChild::spawn(&mut child_command("''"))
+        .unwrap()
+        .with_grace_period(Duration::from_millis(200));
+    let kill = terminate_when_ready();
+    let status = child.wait().unwrap();
+    kill.join().unwrap();
+    assert_eq!(exit_code(&status), 137);
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn test_parse_python_version() {
+    use py_executer_lib::version::PythonVersion;
+
+    assert_eq!(
+        "3.11.7".parse::<PythonVersion>(),
+        Ok(PythonVersion::new(3, 11, 7))
+    );
+    assert_eq!(
+        "3.13.0rc1".parse::<PythonVersion>(),
+        Ok(PythonVersion::new(3, 13, 0))
+    );
+    assert_eq!(
+        "3".parse::<PythonVersion>(),
+        Ok(PythonVersion::new(3, 0, 0))
+    );
+    assert!("python".parse::<PythonVersion>().is_err());
+}
+
+#[test]
+fn test_python_request() {
+    use py_executer_lib::version::{PythonRequest, PythonVersion, VersionSpecifiers};
+
+    let request = PythonRequest::parse("3.11").unwrap();
+    assert_eq!(request.to_string(), "3.11");
+    let PythonRequest::Version { specifiers, .. } = &request else {
+        panic!("expected a version request");
+    };
+    assert!(specifiers.contains(&PythonVersion::new(3, 11, 7)));
+    assert!(!specifiers.contains(&PythonVersion::new(3, 12, 0)));
+
+    let request = PythonRequest::parse("pypy3.10").unwrap();
+    assert_eq!(request.to_string(), "pypy3.10");
+    assert!(matches!(
+        request,
+        PythonRequest::Version { implementation: Some(ref name), .. } if name == "pypy"
+    ));
+
+    let specifiers: VersionSpecifiers = ">=3.10,<3.13".parse().unwrap();
+    assert!(specifiers.contains(&PythonVersion::new(3, 12, 4)));
+    assert!(!specifiers.contains(&PythonVersion::new(3, 13, 0)));
+    assert!(!specifiers.contains(&PythonVersion::new(3, 9, 18)));
+
+    let specifiers: VersionSpecifiers = "~=3.10".parse().unwrap();
+    assert!(specifiers.contains(&PythonVersion::new(3, 12, 0)));
+    assert!(!specifiers.contains(&PythonVersion::new(4, 0, 0)));
+
+    assert!(matches!(
+        PythonRequest::parse("/usr/bin/python3"),
+        Ok(PythonRequest::Path(_))
+    ));
+    assert!(PythonRequest::parse("jython2.7").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interpreter_candidates() {
+    use py_executer_lib::discovery::{InterpreterSource, interpreter_candidates};
+
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let fake_dir = |name: &str, executables: &[&str]| {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        for executable in executables {
+            std::fs::write(dir.join(executable), executable).unwrap();
+        }
+        dir
+    };
+    let first = fake_dir(
+        "first",
+        &[
+            "python",
+            "python3.9",
+            "python3.12",
+            "python3.10",
+            "pypy3.10",
+            "pypy3",
+            "python3",
+            "python3-config",
+            "python3.x",
+        ],
+    );
+    let second = fake_dir("second", &["python3"]);
+    std::os::unix::fs::symlink(first.join("python3.12"), second.join("python3.12")).unwrap();
+
+    let saved = std::env::var_os("PATH");
+    let path = std::env::join_paths([&first, &second]).unwrap();
+    // SAFETY: the other tests reading the environment wait for ENV_LOCK.
+    unsafe { std::env::set_var("PATH", path) };
+    let candidates = interpreter_candidates();
+    match saved {
+        Some(saved) => unsafe { std::env::set_var("PATH", saved) },
+        None => unsafe { std::env::remove_var("PATH") },
+    }
+
+    // pyenv and system interpreters of the machine are listed after the ones on PATH
+    let candidates: Vec<_> = candidates
+        .into_iter()
+        .filter(|(path, _)| path.starts_with(&root))
+        .collect();
+    let expected = [
+        first.join("python3"),
+        first.join("python3.12"),
+        first.join("python3.10"),
+        first.join("python3.9"),
+        first.join("pypy3"),
+        first.join("pypy3.10"),
+        second.join("python3"),
+    ];
+    assert_eq!(
+        candidates,
+        expected.map(|path| (path, InterpreterSource::Path))
+    );
+}
+
+#[test]
+fn test_project_python_request() {
+    use py_executer_lib::version::{PythonRequest, find_project_python_request};
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    std::fs::write(project.join(".python-version"), "system\n3.12\n").unwrap();
+    let (request, _) = find_project_python_request(&project, true)
+        .unwrap()
+        .unwrap();
+    assert_eq!(request, PythonRequest::parse("3.12").unwrap());
+
+    std::fs::write(project.join(".python-version"), "my-virtualenv\n").unwrap();
+    let no_valid_entry = find_project_python_request(&project, true);
+    assert!(no_valid_entry.unwrap().is_none());
+}
+
+#[test]
+fn test_parse_pyvenv_cfg() {
+    use py_executer_lib::venv::PyVenvCfg;
+    use py_executer_lib::version::PythonVersion;
+
+    let cfg = PyVenvCfg::parse(
+        "home = /usr/bin\ninclude-system-site-packages = false\nversion_info = 3.12.1\nuv = 0.5.0\n",
+    );
+    assert_eq!(cfg.home(), Some(PathBuf::from("/usr/bin")));
+    assert_eq!(cfg.version(), Some(PythonVersion::new(3, 12, 1)));
+    assert!(cfg.is_uv());
+    assert_eq!(cfg.was_created_at(&PathBuf::from(".")), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_venv() {
+    use py_executer_lib::venv::{VenvHealth, check_venv};
+    use py_executer_lib::version::{PythonRequest, PythonVersion};
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let home = root.join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    // a venv whose python answers the probe like python 3.12.1
+    let fake_venv = |name: &str, cfg: Option<&str>| {
+        let venv = root.join(name);
+        let bin = venv.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(
+            bin.join("python"),
+            "#!/bin/sh\nprintf '3.12.1\\ncpython\\nx86_64\\n%s\\n' \"$0\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(bin.join("python"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        if let Some(cfg) = cfg {
+            std::fs::write(
+                venv.join("pyvenv.cfg"),
+                cfg.replace("{venv}", &venv.to_string_lossy()),
+            )
+            .unwrap();
+        }
+        venv
+    };
+    let broken = |health: VenvHealth, reason: &str| match health {
+        VenvHealth::Broken(found) => assert!(found.contains(reason), "{}", found),
+        VenvHealth::Healthy(interpreter) => panic!("{} is healthy", interpreter),
+    };
+    let home_line = format!("home = {}\n", home.display());
+
+    let healthy = fake_venv(
+        "my venvs/healthy",
+        Some(&format!(
+            "{}version = 3.12.1\ncommand = /usr/bin/python3 -m venv {{venv}}\n",
+            home_line
+        )),
+    );
+    match check_venv(&healthy, None, true) {
+        VenvHealth::Healthy(interpreter) => {
+            assert_eq!(interpreter.version, PythonVersion::new(3, 12, 1))
+        }
+        VenvHealth::Broken(reason) => panic!("{}", reason),
+    }
+    broken(
+        check_venv(&healthy, Some(&PythonRequest::parse("3.13").unwrap()), true),
+        "does not satisfy the request",
+    );
+
+    let missing_home = fake_venv(
+        "missing_home",
+        Some(&format!("home = {}\n", root.join("removed").display())),
+    );
+    broken(
+        check_venv(&missing_home, None, true),
+        "base interpreter directory",
+    );
+
+    let moved = fake_venv(
+        "moved",
+        Some(&format!(
+            "{}command = /usr/bin/python3 -m venv {}\n",
+            home_line,
+            home.display()
+        )),
+    );
+    broken(
+        check_venv(&moved, None, true),
+        "created at another location",
+    );
+
+    let other_version = fake_venv(
+        "other_version",
+        Some(&format!("{}version_info = 3.11.4\n", home_line)),
+    );
+    broken(
+        check_venv(&other_version, None, true),
+        "created for python 3.11.4 but runs python 3.12.1",
+    );
+
+    let no_cfg = fake_venv("no_cfg", None);
+    broken(check_venv(&no_cfg, None, true), "no pyvenv.cfg");
+    assert!(matches!(
+        check_venv(&no_cfg, None, false),
+        VenvHealth::Healthy(_)
+    ));
+}
+
+#[test]
+fn test_parse_script_metadata() {
+    use py_executer_lib::script::ScriptMetadata;
+
+    let script = r#"# /// script
+# requires-python = ">=3.11"
+# dependencies = [
+#   "requests<3",
+#   "rich",
+# ]
+# ///
+
+import requests
+"#;
+    let metadata = ScriptMetadata::parse(script).unwrap().unwrap();
+    assert_eq!(metadata.requires_python.as_deref(), Some(">=3.11"));
+    assert_eq!(metadata.dependencies, vec!["requests<3", "rich"]);
+
+    assert_eq!(ScriptMetadata::parse("print('hello')\n").unwrap(), None);
+    assert!(ScriptMetadata::parse("# /// script\n# dependencies = []\n").is_err());
+
+    // the venv of the script changes with its metadata and the python it is created for
+    let hash = metadata.hash(None);
+    let newer_python = ScriptMetadata {
+        requires_python: Some(">=3.12".to_string()),
+        ..metadata.clone()
+    };
+    let fewer_dependencies = ScriptMetadata {
+        dependencies: vec!["requests<3".to_string()],
+        ..metadata.clone()
+    };
+    let request = py_executer_lib::version::parse_request("3.11").unwrap();
+    assert_eq!(metadata.hash(None), hash);
+    assert_ne!(newer_python.hash(None), hash);
+    assert_ne!(fewer_dependencies.hash(None), hash);
+    assert_ne!(metadata.hash(Some(&request)), hash);
+}
+
+#[test]
+fn test_fingerprint() {
+    use py_executer_lib::fingerprint::Fingerprint;
+
+    let digest = |pairs: &[(&str, &str)]| {
+        let mut fingerprint = Fingerprint::new();
+        for (key, value) in pairs {
+            fingerprint.value(key, value);
+        }
+        fingerprint.finish()
+    };
+    assert_eq!(
+        digest(&[("python", "3.12.1")]),
+        digest(&[("python", "3.12.1")])
+    );
+    assert_ne!(
+        digest(&[("python", "3.12.1")]),
+        digest(&[("python", "3.12.2")])
+    );
+    assert_ne!(digest(&[("ab", "c")]), digest(&[("a", "bc")]));
+
+    let mut missing = Fingerprint::new();
+    missing.file(&PathBuf::from("does/not/exist.txt"));
+    assert_ne!(missing.finish(), Fingerprint::new().finish());
+}
+
+#[test]
+fn test_store_venv_path() {
+    use py_executer_lib::store::store_venv_path;
+
+    let project = PathBuf::from("/path/to/myproject");
+    let Some(venv) = store_venv_path(&project, "hash") else {
+        return;
+    };
+    let name = venv.file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("myproject-"));
+    assert_eq!(name.len(), "myproject-".len() + 16);
+    assert_eq!(store_venv_path(&project, "hash"), Some(venv.clone()));
+    assert_ne!(store_venv_path(&project, "other"), Some(venv));
+}
+
+#[test]
+fn test_venv_registry() {
+    use py_executer_lib::registry::{VenvRegistry, parse_duration};
+    use std::time::Duration;
+
+    assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86400)));
+    assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+    assert!(parse_duration("30").is_err());
+    assert!(parse_duration("d").is_err());
+    assert!(parse_duration("99999999999999w").is_err());
+
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("venvs.toml");
+    let mut registry = VenvRegistry::load(&path).unwrap();
+    assert!(registry.records().is_empty());
+    registry.record_created(&PathBuf::from("/project/.venv"), &PathBuf::from("/project"));
+    assert!(registry.touch(&PathBuf::from("/project/.venv")));
+    assert!(!registry.touch(&PathBuf::from("/other/.venv")));
+    registry.save().unwrap();
+
+    let loaded = VenvRegistry::load(&path).unwrap();
+    assert_eq!(loaded.records().len(), 1);
+    assert_eq!(loaded.records()[0].project, PathBuf::from("/project"));
+    assert!(!loaded.records()[0].is_older_than(Duration::from_secs(3600)));
+}
+
+#[test]
+fn test_venvs_gc() {
+    use py_executer_lib::lock::{FileLock, venv_use_lock_path};
+    use py_executer_lib::registry::VenvRegistry;
+    use std::time::Duration;
+
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let cache = root.join("cache");
+    let fake_venv = |project: &str| {
+        let venv = root.join(project).join(".venv");
+        std::fs::create_dir_all(&venv).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "version = 3.12.1\n").unwrap();
+        venv
+    };
+    let used = fake_venv("used");
+    let unused = fake_venv("unused");
+    let registry = |venv: &std::path::Path| {
+        format!(
+            "[[venv]]\npath = '{}'\nproject = '{}'\ncreated = 0\nlast_used = 0\n",
+            venv.display(),
+            venv.parent().unwrap().display()
+        )
+    };
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(
+        cache.join("venvs.toml"),
+        registry(&used) + &registry(&unused),
+    )
+    .unwrap();
+
+    let saved = std::env::var_os("PY_EXECUTER_CACHE_DIR");
+    // SAFETY: the other tests reading the environment wait for ENV_LOCK.
+    unsafe { std::env::set_var("PY_EXECUTER_CACHE_DIR", &cache) };
+    let use_lock =
+        FileLock::acquire_shared(&venv_use_lock_path(&used), Duration::ZERO, || {}).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_py_executer"))
+        .args(["venvs", "gc", "--older-than", "1d"])
+        .env("PY_EXECUTER_CACHE_DIR", &cache)
+        .output()
+        .unwrap();
+    drop(use_lock);
+    let registry = VenvRegistry::load(&cache.join("venvs.toml"));
+    match saved {
+        Some(saved) => unsafe { std::env::set_var("PY_EXECUTER_CACHE_DIR", saved) },
+        None => unsafe { std::env::remove_var("PY_EXECUTER_CACHE_DIR") },
+    }
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("used by another py_executer"));
+    assert!(used.exists());
+    assert!(!unused.exists());
+    let registry = registry.unwrap();
+    assert_eq!(registry.records().len(), 1);
+    assert_eq!(registry.records()[0].path, used);
+}
+
+#[test]
+fn test_file_lock() {
+    use py_executer_lib::lock::FileLock;
+    use std::time::Duration;
+
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("py_executer.lock");
+    let lock = FileLock::acquire(&path, Duration::ZERO, || {}).unwrap();
+    let mut waited = false;
+    let result = FileLock::acquire(&path, Duration::from_millis(200), || waited = true);
+    assert!(matches!(
+        result,
+        Err(py_executer_lib::Error::LockTimeout { .. })
+    ));
+    assert!(waited);
+
+    drop(lock);
+    assert!(FileLock::acquire(&path, Duration::ZERO, || {}).is_ok());
+
+    // shared locks only exclude the exclusive lock, e.g. the cleanup of a venv in use
+    let first = FileLock::acquire_shared(&path, Duration::ZERO, || {}).unwrap();
+    let second = FileLock::acquire_shared(&path, Duration::ZERO, || {}).unwrap();
+    assert!(FileLock::acquire(&path, Duration::ZERO, || {}).is_err());
+    drop(first);
+    assert!(FileLock::acquire(&path, Duration::ZERO, || {}).is_err());
+    drop(second);
+    assert!(FileLock::acquire(&path, Duration::ZERO, || {}).is_ok());
+}
+
+#[test]
+fn test_resolve_dependencies() {
+    use py_executer_lib::deps::Dependencies;
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    let requirements = project.join("requirements.txt");
+    let constraints = project.join("constraints.txt");
+    std::fs::write(&requirements, "requests\n").unwrap();
+    std::fs::write(&constraints, "requests<3\n").unwrap();
+
+    let default = Dependencies::resolve(&project, &[], &[], Default::default()).unwrap();
+    assert_eq!(default.requirements, vec![requirements]);
+    let constrained = Dependencies::resolve(
+        &project,
+        &[],
+        std::slice::from_ref(&constraints),
+        Default::default(),
+    )
+    .unwrap();
+    assert_eq!(
+        constrained.constraints,
+        vec![constraints.canonicalize().unwrap()]
+    );
+    assert_ne!(
+        default.hash(&project, None),
+        constrained.hash(&project, None)
+    );
+    let missing = Dependencies::resolve(
+        &project,
+        &[project.join("missing.txt")],
+        &[],
+        Default::default(),
+    );
+
+    assert!(matches!(
+        missing,
+        Err(py_executer_lib::Error::DependencyFileNotFound { .. })
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dependency_fingerprint() {
+    use py_executer_lib::deps::{Dependencies, SyncMode, SyncTarget};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    let requirements = project.join("requirements.txt");
+    std::fs::write(&requirements, "requests\n").unwrap();
+    // fake interpreters answering the probe
+    let fake_python = |name: &str, version: &str| {
+        let path = project.join(name);
+        std::fs::write(
+            &path,
+            format!("#!/bin/sh\nprintf '{version}\\nCPython\\nx86_64\\n/opt/{name}\\n'\n"),
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    let python311 = fake_python("python3.11", "3.11.4");
+    let python312 = fake_python("python3.12", "3.12.1");
+    let unlocked = SyncMode::default();
+    let locked = SyncMode {
+        locked: true,
+        ..SyncMode::default()
+    };
+
+    let dependencies = Dependencies::resolve(&project, &[], &[], Default::default()).unwrap();
+    let venv = project.join(".venv");
+    let fingerprint = |python_exec_path: &Path, mode: &SyncMode| {
+        dependencies.fingerprint(&SyncTarget {
+            runtime_path: &project,
+            uv_path: "",
+            venv: &venv,
+            explicit_source: None,
+            python_exec_path,
+            mode,
+            workspace: None,
+            member: None,
+        })
+    };
+    let synced = fingerprint(&python311, &unlocked);
+
+    assert_eq!(fingerprint(&python311, &unlocked), synced);
+    assert_ne!(fingerprint(&python312, &unlocked), synced);
+    assert_ne!(fingerprint(&python311, &locked), synced);
+    std::fs::write(&requirements, "requests==2.32.3\n").unwrap();
+    assert_ne!(fingerprint(&python311, &unlocked), synced);
+}
+
+#[test]
+fn test_dependency_selection() {
+    use py_executer_lib::deps::{Dependencies, DependencySelection};
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    std::fs::write(
+        project.join("pyproject.toml"),
+        "[tool.py_executer]\nextras = [\"cli\"]\ngroups = [\"lint\"]\nno-dev = true\n",
+    )
+    .unwrap();
+
+    let from_config = Dependencies::resolve(&project, &[], &[], Default::default()).unwrap();
+    assert_eq!(
+        from_config.selection.sync_args(),
+        ["--extra", "cli", "--group", "lint", "--no-dev"]
+    );
+    let overridden = DependencySelection {
+        groups: vec!["test".to_string()],
+        ..Default::default()
+    };
+    let from_args = Dependencies::resolve(&project, &[], &[], overridden).unwrap();
+    assert_eq!(
+        from_args.selection.sync_args(),
+        ["--extra", "cli", "--group", "test", "--no-dev"]
+    );
+    let all_extras = DependencySelection {
+        all_extras: true,
+        ..Default::default()
+    };
+    let from_args = Dependencies::resolve(&project, &[], &[], all_extras).unwrap();
+    assert_eq!(
+        from_args.selection.sync_args(),
+        ["--all-extras", "--group", "lint", "--no-dev"]
+    );
+    let config_all_extras = DependencySelection {
+        all_extras: true,
+        ..Default::default()
+    };
+    let extras = DependencySelection {
+        extras: vec!["gui".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(extras.or(config_all_extras).sync_args(), ["--extra", "gui"]);
+
+    std::fs::write(
+        project.join("pyproject.toml"),
+        "[tool.py_executer]\nno-dev = 1\n",
+    )
+    .unwrap();
+    let invalid = Dependencies::resolve(&project, &[], &[], Default::default());
+    assert!(matches!(
+        invalid,
+        Err(py_executer_lib::Error::InvalidPyProject { .. })
+    ));
+}
+
+#[test]
+fn test_sync_mode() {
+    use py_executer_lib::deps::SyncMode;
+
+    assert!(SyncMode::default().sync_args().is_empty());
+    let mode = SyncMode {
+        locked: true,
+        offline: true,
+        find_links: vec!["wheels".to_string()],
+        ..Default::default()
+    };
+    assert!(mode.uses_lock());
+    assert_eq!(
+        mode.sync_args(),
+        ["--locked", "--offline", "--find-links", "wheels"]
+    );
+    assert_eq!(
+        mode.index_args(false),
+        ["--no-index", "--find-links", "wheels"]
+    );
+}
+
+#[test]
+fn test_install_summary() {
+    use py_executer_lib::installer::InstallSummary;
+
+    let pip = InstallSummary::parse(
+        "Requirement already satisfied: idna in ./.venv/lib\n\
+         Attempting uninstall: charset-normalizer\n\
+         Successfully uninstalled charset-normalizer-3.3.0\n\
+         Successfully installed charset-normalizer-3.3.2 requests-2.32.3\n",
+    );
+    assert_eq!(pip.added, ["requests"]);
+    assert_eq!(pip.updated, ["charset-normalizer"]);
+    assert!(pip.removed.is_empty());
+
+    let uv = InstallSummary::parse(" - six==1.16.0\n ~ project==0.1.0 (from file:///project)\n");
+    assert_eq!(uv.removed, ["six"]);
+    assert_eq!(uv.updated, ["project"]);
+    let poetry = InstallSummary::parse(
+        "  - Installing certifi (2024.2.2)\n  - Updating idna (3.6 -> 3.7)\n  - Removing six (1.16.0)\n",
+    );
+    assert_eq!(poetry.to_string(), "1 added, 1 removed, 1 updated");
+    assert_eq!(
+        InstallSummary::parse("Audited 3 packages in 1ms\n").to_string(),
+        "no changes"
+    );
+}
+
+#[test]
+fn test_append_to_pythonpath() {
+    use py_executer_lib::{append_to_pythonpath, pythonpath_roots};
+    use std::ffi::OsString;
+
+    let existing = std::env::join_paths(["/a/bc", "/d"]).unwrap();
+    let pythonpath = append_to_pythonpath(
+        Some(&existing),
+        &[PathBuf::from("/a/b"), PathBuf::from("/d/")],
+    )
+    .unwrap();
+    assert_eq!(
+        std::env::split_paths(&pythonpath).collect::<Vec<_>>(),
+        [
+            PathBuf::from("/a/bc"),
+            PathBuf::from("/d"),
+            PathBuf::from("/a/b")
+        ]
+    );
+    assert_eq!(
+        append_to_pythonpath(Some(&OsString::new()), &[PathBuf::from("/a")]).unwrap(),
+        "/a"
+    );
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    std::fs::create_dir_all(project.join("src").join("package")).unwrap();
+    assert_eq!(
+        pythonpath_roots(&project, &[]),
+        std::slice::from_ref(&project)
+    );
+    std::fs::write(project.join("src").join("package").join("__init__.py"), "").unwrap();
+    let roots = pythonpath_roots(&project, &[]);
+    assert_eq!(roots, [project.clone(), project.join("src")]);
+}
+
+#[test]
+fn test_activation() {
+    use py_executer_lib::activation::Activation;
+
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let temp = tempfile::tempdir().unwrap();
+    let env_path = temp.path().to_path_buf();
+    std::fs::write(env_path.join("pyvenv.cfg"), "prompt = 'tools'\n").unwrap();
+    let venv = Activation::new(&env_path);
+    assert_eq!(
+        venv.get("VIRTUAL_ENV_PROMPT"),
+        Some(std::ffi::OsStr::new("tools"))
+    );
+    let path = venv.get("PATH").unwrap();
+    let first = std::env::split_paths(path).next().unwrap();
+    assert!(first.starts_with(&env_path));
+
+    std::fs::create_dir_all(env_path.join("conda-meta")).unwrap();
+    std::fs::write(
+        env_path.join("conda-meta").join("state"),
+        r#"{"env_vars": {"MODEL_DIR": "/data/models"}}"#,
+    )
+    .unwrap();
+    let conda = Activation::new(&env_path);
+    assert_eq!(conda.get("CONDA_PREFIX"), Some(env_path.as_os_str()));
+    assert!(conda.get("VIRTUAL_ENV").is_none());
+    assert!(conda.unset.contains(&"VIRTUAL_ENV".to_string()));
+    assert_eq!(
+        conda.get("MODEL_DIR"),
+        Some(std::ffi::OsStr::new("/data/models"))
+    );
+    assert!(conda.get("CONDA_SHLVL").is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_find_explicit_venv() {
+    use py_executer_lib::path::{VenvSource, find_explicit_venv};
+    use std::ffi::OsStr;
+
+    const VARIABLES: [&str; 3] = ["VIRTUAL_ENV", "CONDA_PREFIX", "CONDA_DEFAULT_ENV"];
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    // SAFETY: the other tests using these variables wait for ENV_LOCK.
+    let set_env = |values: [Option<&OsStr>; 3]| {
+        for (key, value) in VARIABLES.into_iter().zip(values) {
+            match value {
+                Some(value) => unsafe { std::env::set_var(key, value) },
+                None => unsafe { std::env::remove_var(key) },
+            }
+        }
+    };
+    let saved = VARIABLES.map(std::env::var_os);
+
+    let temp = tempfile::tempdir().unwrap();
+    let fake_env = |name: &str, with_python: bool| {
+        let path = temp.path().canonicalize().unwrap().join(name);
+        std::fs::create_dir_all(path.join("bin")).unwrap();
+        if with_python {
+            std::fs::write(path.join("bin").join("python"), "").unwrap();
+        }
+        path
+    };
+    let argument = fake_env("argument", true);
+    let virtual_env = fake_env("virtual_env", true);
+    let conda = fake_env("conda", true);
+    let broken = fake_env("broken", false);
+    let analysis = Some(OsStr::new("analysis"));
+    let find = |venv: Option<&std::path::Path>| find_explicit_venv(venv, true).unwrap();
+
+    set_env([
+        Some(virtual_env.as_os_str()),
+        Some(conda.as_os_str()),
+        analysis,
+    ]);
+    let from_argument = find(Some(&argument));
+    let from_virtual_env = find(None);
+    set_env([None, Some(conda.as_os_str()), analysis]);
+    let from_conda = find(None);
+    set_env([None, Some(conda.as_os_str()), Some(OsStr::new("base"))]);
+    let from_base = find(None);
+    set_env([Some(broken.as_os_str()), Some(conda.as_os_str()), analysis]);
+    let skipped_broken = find(None);
+    let broken_argument = find_explicit_venv(Some(&broken), true);
+    set_env(saved.each_ref().map(Option::as_deref));
+
+    assert_eq!(from_argument, Some((argument, VenvSource::Argument)));
+    assert_eq!(
+        from_virtual_env,
+        Some((virtual_env, VenvSource::VirtualEnv))
+    );
+    assert_eq!(from_conda, Some((conda.clone(), VenvSource::Conda)));
+    assert_eq!(from_base, None);
+    assert_eq!(skipped_broken, Some((conda, VenvSource::Conda)));
+    assert!(matches!(broken_argument, Err(Error::InvalidVenv { .. })));
+}
+
+#[test]
+fn test_find_project_root() {
+    use py_executer_lib::project::find_project_root;
+
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().to_path_buf();
+    let tools = root.join("tools").join("scripts");
+    std::fs::create_dir_all(&tools).unwrap();
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    assert_eq!(find_project_root(&tools), Some((root.clone(), ".git")));
+
+    std::fs::write(root.join("tools").join("requirements.txt"), "").unwrap();
+    assert_eq!(
+        find_project_root(&tools),
+        Some((root.join("tools"), "requirements.txt"))
+    );
+}
+
+#[test]
+fn test_uv_workspace() {
+    use py_executer_lib::workspace::Workspace;
+
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().to_path_buf();
+    let root = root.canonicalize().unwrap();
+    std::fs::write(
+        root.join("pyproject.toml"),
+        "[tool.uv.workspace]\nmembers = [\"pack*/*\"]\nexclude = [\"packages/legacy\"]\n",
+    )
+    .unwrap();
+    for name in ["api", "cli", "legacy"] {
+        let member = root.join("packages").join(name);
+        std::fs::create_dir_all(member.join("tools")).unwrap();
+        std::fs::write(
+            member.join("pyproject.toml"),
+            format!("[project]\nname = \"{}\"\n", name),
+        )
+        .unwrap();
+    }
+
+    let workspace = Workspace::find(&root.join("packages").join("cli"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(workspace.root, root);
+    let names: Vec<&str> = workspace.members.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["api", "cli"]);
+    let script = root.join("packages").join("cli").join("tools").join("x.py");
+    assert_eq!(workspace.member_of(&script).unwrap().name, "cli");
+    assert!(workspace.member_of(&root.join("scripts")).is_none());
+
+    let outside = Workspace::find(&root.join("packages").join("legacy")).unwrap();
+    assert!(outside.is_none());
+}
+
+#[test]
+fn test_package_managers() {
+    use py_executer_lib::managers::PackageManager;
+
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().to_path_buf();
+    std::fs::write(
+        project.join("poetry.lock"),
+        r#"
+[[package]]
+name = "requests"
+version = "2.32.3"
+
+[[package]]
+name = "pywin32"
+version = "306"
+markers = "sys_platform == \"win32\""
+
+[[package]]
+name = "tool"
+version = "0.1.0"
+[package.source]
+type = "git"
+url = "https://example.com/tool.git"
+resolved_reference = "abc123"
+
+[[package]]
+name = "extra-only"
+version = "1.0"
+optional = true
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("Pipfile.lock"),
+        r#"{"default": {"idna": {"version": "==3.7"}}, "develop": {"pytest": {"version": "==8.2.0", "extras": ["testing"]}}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        PackageManager::detect(&project),
+        Some(PackageManager::Poetry)
+    );
+    assert_eq!(
+        PackageManager::Poetry
+            .export_requirements(&project)
+            .unwrap(),
+        [
+            "requests==2.32.3",
+            "pywin32==306 ; sys_platform == \"win32\"",
+            "tool @ git+https://example.com/tool.git@abc123",
+        ]
+    );
+    assert_eq!(
+        PackageManager::Pipenv
+            .export_requirements(&project)
+            .unwrap(),
+        ["idna==3.7", "pytest[testing]==8.2.0"]
+    );
+
+    // a uv.lock takes precedence
+    std::fs::write(project.join("uv.lock"), "").unwrap();
+    assert_eq!(PackageManager::detect(&project), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_conda_environment_file() {
+    use py_executer_lib::conda::{CREATED_MARKER_FILE_NAME, CondaKind, CondaTool, EnvironmentFile};
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().to_path_buf();
+    let path = root.join("environment.yml");
+    std::fs::write(
+        &path,
+        "name: \"analysis\" # the env\nchannels:\n  - conda-forge\ndependencies:\n  - name: numpy\n",
+    )
+    .unwrap();
+    assert_eq!(EnvironmentFile::find(&root), Some(path.clone()));
+    let environment = EnvironmentFile::read(&path).unwrap();
+    assert_eq!(environment.name.as_deref(), Some("analysis"));
+    assert!(environment.prefix.is_none());
+
+    std::fs::write(&path, "dependencies:\n  - python=3.12\n").unwrap();
+    assert!(matches!(
+        EnvironmentFile::read(&path),
+        Err(Error::InvalidEnvironmentFile { .. })
+    ));
+
+    // a fake conda listing its environments and logging its calls
+    let tool_path = root.join("conda");
+    std::fs::write(
+        &tool_path,
+        "#!/bin/sh\necho \"$@ CONDA_OFFLINE=$CONDA_OFFLINE\" >> \"$(dirname \"$0\")/calls.log\"\n\
+         echo '{\"envs\": [\"/opt/conda\", \"/opt/conda/envs/analysis\"]}'\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&tool_path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let tool = CondaTool {
+        path: tool_path,
+        kind: CondaKind::Conda,
+    };
+    assert_eq!(
+        tool.env_prefix("analysis"),
+        Some(PathBuf::from("/opt/conda/envs/analysis"))
+    );
+    assert_eq!(tool.env_prefix("base"), Some(PathBuf::from("/opt/conda")));
+    assert_eq!(tool.env_prefix("conda"), None);
+
+    // an environment of the user is not pruned, one created by py_executer is
+    let last_update = || {
+        let calls = std::fs::read_to_string(root.join("calls.log")).unwrap();
+        let update = calls
+            .lines()
+            .rfind(|call| call.starts_with("env update"))
+            .unwrap()
+            .to_string();
+        (
+            update.contains("--prune"),
+            update.ends_with("CONDA_OFFLINE=true"),
+        )
+    };
+    let prefix = root.join("env");
+    std::fs::create_dir_all(&prefix).unwrap();
+    std::fs::write(&path, format!("prefix: {}\n", prefix.display())).unwrap();
+    tool.prepare(&EnvironmentFile::read(&path).unwrap(), false, true)
+        .unwrap();
+    assert_eq!(last_update(), (false, false));
+
+    std::fs::write(prefix.join(CREATED_MARKER_FILE_NAME), "").unwrap();
+    std::fs::write(
+        &path,
+        format!("prefix: {}\ndependencies: []\n", prefix.display()),
+    )
+    .unwrap();
+    tool.prepare(&EnvironmentFile::read(&path).unwrap(), true, true)
+        .unwrap();
+    assert_eq!(last_update(), (true, true));
+}
+
+#[test]
+fn test_runner() {
+    use py_executer_lib::runner::{RunSpec, Runner};
+
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let Some(python) = native_python() else {
+        eprintln!("no python found, skipping");
+        return;
+    };
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().canonicalize().unwrap();
+    let venv = project.join("env");
+    create_test_venv(&python, &venv);
+    std::fs::write(
+        project.join("report.py"),
+        r#"import os, sys
+with open(sys.argv[1], "w") as out:
+    out.write(os.environ["VIRTUAL_ENV"] + "\n" + os.environ["PY_EXECUTER_TEST_GREETING"])
+sys.exit(3)
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("first.env"),
+        "PY_EXECUTER_TEST_GREETING=first\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("second.env"),
+        "PY_EXECUTER_TEST_GREETING=second\n",
+    )
+    .unwrap();
+
+    let report = project.join("report.txt");
+    let spec = RunSpec::new(project.join("report.py"))
+        .project(&project)
+        .venv(&venv)
+        .env_file(project.join("first.env"))
+        .env_file(project.join("second.env"))
+        .quiet(true)
+        .arg(report.to_string_lossy());
+    let outcome = Runner::new(spec).run().unwrap();
+    assert_eq!(outcome.code(), 3);
+    assert_eq!(outcome.venv.as_deref(), Some(venv.as_path()));
+
+    // the later env file wins, and the values only reach the script
+    let report = std::fs::read_to_string(&report).unwrap();
+    assert_eq!(report, format!("{}\nsecond", venv.display()));
+    assert_eq!(std::env::var_os("PY_EXECUTER_TEST_GREETING"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inline_script_in_broken_workspace() {
+    use py_executer_lib::runner::{RunSpec, Runner};
+
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let temp = tempfile::tempdir().unwrap();
+    let project = temp.path().canonicalize().unwrap();
+    std::fs::write(
+        project.join("pyproject.toml"),
+        "[project]\nname = \"broken\"\n\n[tool.uv.workspace]\nmembers = 42\n",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("tool.py"),
+        "# /// script\n# dependencies = []\n# ///\nprint('hello')\n",
+    )
+    .unwrap();
+    let bin = project.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let path = stub_uv(&bin, "echo \"$@\" > \"$(dirname \"$0\")/args\"; exit 4");
+
+    let saved = std::env::var_os("PATH");
+    // SAFETY: the other tests reading the environment wait for ENV_LOCK.
+    unsafe { std::env::set_var("PATH", &path) };
+    let spec = RunSpec::new(project.join("tool.py"))
+        .project(&project)
+        .package("missing")
+        .quiet(true);
+    let outcome = Runner::new(spec).run();
+    match saved {
+        Some(saved) => unsafe { std::env::set_var("PATH", saved) },
+        None => unsafe { std::env::remove_var("PATH") },
+    }
+
+    // the workspace and the --package are never looked at, uv runs the script on its own
+    assert_eq!(outcome.unwrap().code(), 4);
+    let args = std::fs::read_to_string(bin.join("args")).unwrap();
+    assert!(args.starts_with("run --script --quiet"));
+    assert!(args.trim_end().ends_with("tool.py"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_uv_passthrough() {
+    let _env = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let temp = tempfile::tempdir().unwrap();
+    let bin = temp.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    let path = stub_uv(&bin, "echo \"$@\"; exit 42");
+    let py_executer = |path: &std::ffi::OsStr| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_py_executer"))
+            .args(["uv", "pip", "install", "--offline", "requests"])
+            .env("PATH", path)
+            .output()
+            .unwrap()
+    };
+
+    let output = py_executer(&path);
+    assert_eq!(output.status.code(), Some(42));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "pip install --offline requests\n"
+    );
+
+    // only `which` is left on PATH, so uv cannot be found
+    let which = py_executer_lib::find_executable("which").unwrap();
+    let no_uv = temp.path().join("no_uv");
+    std::fs::create_dir_all(&no_uv).unwrap();
+    std::os::unix::fs::symlink(which, no_uv.join("which")).unwrap();
+    let output = py_executer(no_uv.as_os_str());
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("uv not installed"));
+    assert!(stderr.contains("install uv"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_create_venv() {
+    use py_executer_lib::path::{VenvOptions, get_venv_path};
+    use py_executer_lib::venv::ensure_pip;
+    use py_executer_lib::version::PythonRequest;
+    use std::os::unix::fs::PermissionsExt;
+
+    let Some(python) = native_python() else {
+        eprintln!("no python found, skipping");
+        return;
+    };
+    let temp = tempfile::tempdir().unwrap();
+    let root = temp.path().canonicalize().unwrap();
+    let has_pip = |venv: &std::path::Path| {
+        std::process::Command::new(venv.join("bin").join("python"))
+            .args(["-m", "pip", "--version"])
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    // a removed venv is never recorded in the registry of the user
+    let options = |uv_path, python_native_path, python_request| VenvOptions {
+        uv_path,
+        python_native_path,
+        python_request,
+        quiet: true,
+        clean: true,
+        rebuild: false,
+        central: false,
+        offline: true,
+        dependency_hash: "",
+    };
+    let python_path = python.to_string_lossy();
+
+    // without uv, the venv is created with `python -m venv`, including pip
+    let project = root.join("native");
+    std::fs::create_dir_all(&project).unwrap();
+    let mut files = Vec::new();
+    let venv = get_venv_path(&project, &options("", &python_path, None), &mut files).unwrap();
+    assert_eq!(venv, project.join(".venv"));
+    assert_eq!(files, std::slice::from_ref(&venv));
+    assert!(has_pip(&venv));
+
+    // with uv, `uv venv --seed` is run, the stub creates the venv with the native python
+    let project = root.join("uv");
+    std::fs::create_dir_all(&project).unwrap();
+    let uv = root.join("uv-stub");
+    std::fs::write(
+        &uv,
+        format!(
+            "#!/bin/sh\necho \"$@\" > \"$0.args\"\nexec '{}' -m venv --without-pip \"$3\"\n",
+            python_path
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&uv, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let request = PythonRequest::parse("3").unwrap();
+    let uv_path = uv.to_string_lossy();
+    let venv = get_venv_path(
+        &project,
+        &options(&uv_path, &python_path, Some(&request)),
+        &mut Vec::new(),
+    )
+    .unwrap();
+    let args = std::fs::read_to_string(root.join("uv-stub.args")).unwrap();
+    assert_eq!(
+        args.trim_end(),
+        format!("venv --seed {} --python 3 --offline", venv.display())
+    );
+
+    // the stub seeded no pip, so it is bootstrapped
+    assert!(!has_pip(&venv));
+    ensure_pip(&venv.join("bin").join("python"), true).unwrap();
+    assert!(has_pip(&venv));
+
+    // an interpreter that fails creates no venv and bootstraps no pip
+    let broken = root.join("broken-python");
+    std::fs::write(&broken, "#!/bin/sh\necho broken >&2\nexit 1\n").unwrap();
+    std::fs::set_permissions(&broken, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let project = root.join("broken");
+    std::fs::create_dir_all(&project).unwrap();
+    let broken_path = broken.to_string_lossy();
+    let result = get_venv_path(&project, &options("", &broken_path, None), &mut Vec::new());
+    assert!(matches!(
+        result,
+        Err(Error::VenvCreationFailed { ref stderr, .. }) if stderr.trim() == "broken"
+    ));
+    assert!(matches!(
+        ensure_pip(&broken, true),
+        Err(Error::DependencySyncFailed { ref tool, .. }) if tool == "ensurepip"
+    ));
+}